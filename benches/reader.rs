@@ -0,0 +1,161 @@
+use std::{
+	io::{self, BufReader, Cursor, Read, Write},
+	time::Instant,
+};
+
+use ogg::*;
+
+/// * Count the read calls of the reader
+#[derive(Debug)]
+struct CountingReader<R: Read> {
+	inner: R,
+	reads: usize,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self.reads += 1;
+		self.inner.read(buf)
+	}
+}
+
+/// * The baseline: the old reading loop of `OggStreamReader`, which removes each packet from the front of the cache with `drain()`
+/// * Its refills are sized by the packet length, which is 0 until the segment table is complete, like the old loop.
+fn read_with_drain<R: Read>(reader: &mut R, read_size: usize) -> usize {
+	let mut cached_bytes = Vec::<u8>::new();
+	let mut num_packets = 0usize;
+	loop {
+		let mut packet_length = 0usize;
+		match OggPacket::from_bytes(&cached_bytes, &mut packet_length) {
+			Ok(_) => {
+				cached_bytes.drain(..packet_length);
+				num_packets += 1;
+			}
+			Err(e) => match e.get_ref().and_then(|e|e.downcast_ref::<OggError>()) {
+				Some(OggError::UnexpectedEof{..}) => {
+					let mut buf = vec![0u8; packet_length.max(read_size)];
+					let read = reader.read(&mut buf).unwrap();
+					if read == 0 {
+						return num_packets;
+					}
+					cached_bytes.extend(&buf[..read]);
+				}
+				_ => panic!("{e}"),
+			}
+		}
+	}
+}
+
+fn main() {
+	let mut stream = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut stream, 0x12345678);
+		for i in 0..20000u64 {
+			writer.write_all(&[i as u8; 64]).unwrap();
+			writer.flush_page(i).unwrap();
+		}
+	}
+
+	let start = Instant::now();
+	let mut reader = OggStreamReader::new(Cursor::new(&stream));
+	let mut num_packets = 0usize;
+	while reader.get_packet().unwrap().is_some() {
+		num_packets += 1;
+	}
+	let elapsed = start.elapsed();
+	println!("Read {num_packets} small packets ({} bytes) in {elapsed:?}", stream.len());
+
+	// The drain() baseline moves the cached bytes after each packet, which costs more with larger reads.
+	for read_size in [2048, 65536] {
+		let start = Instant::now();
+		let num_packets = read_with_drain(&mut Cursor::new(&stream), read_size);
+		let elapsed = start.elapsed();
+		println!("Read {num_packets} small packets with the read size {read_size} with the drain() baseline in {elapsed:?}");
+
+		let start = Instant::now();
+		let mut reader = OggStreamReader::new(Cursor::new(&stream));
+		reader.set_read_size(read_size);
+		let mut num_packets = 0usize;
+		while reader.get_packet().unwrap().is_some() {
+			num_packets += 1;
+		}
+		let elapsed = start.elapsed();
+		println!("Read {num_packets} small packets with the read size {read_size} with the offset cache in {elapsed:?}");
+	}
+
+	let mut packet = OggPacket::new(0x12345678, OggPacketType::Continuation, 1);
+	packet.write(&[0x55u8; OggPacket::MAX_PAYLOAD]);
+	let large_page = packet.into_bytes();
+	let start = Instant::now();
+	let mut checksum = 0u32;
+	for _ in 0..1000 {
+		checksum = checksum.wrapping_add(OggPacket::get_checksum(&large_page).unwrap());
+	}
+	let elapsed = start.elapsed();
+	println!("Checksummed a {} bytes page 1000 times in {elapsed:?} (0x{checksum:08x})", large_page.len());
+
+	let chunk = [0x55u8; 255];
+	for reserve in [false, true] {
+		let start = Instant::now();
+		let mut reallocations = 0usize;
+		for _ in 0..1000 {
+			let mut packet = if reserve {
+				OggPacket::with_capacity(0x12345678, OggPacketType::Continuation, 1, 60000)
+			} else {
+				OggPacket::new(0x12345678, OggPacketType::Continuation, 1)
+			};
+			let mut capacity = packet.data.capacity();
+			while packet.get_inner_data_size() + chunk.len() <= 60000 {
+				packet.write(&chunk);
+				if packet.data.capacity() != capacity {
+					capacity = packet.data.capacity();
+					reallocations += 1;
+				}
+			}
+		}
+		let elapsed = start.elapsed();
+		let name = if reserve {"with_capacity()"} else {"new()"};
+		println!("Wrote 1000 pages of 60000 bytes with {name} in {elapsed:?}, {reallocations} reallocations");
+	}
+
+	let start = Instant::now();
+	let mut reader = OggStreamReader::new(BufReader::new(Cursor::new(&stream)));
+	let mut num_packets = 0usize;
+	while reader.get_packet().unwrap().is_some() {
+		num_packets += 1;
+	}
+	let elapsed = start.elapsed();
+	println!("Read {num_packets} small packets from a BufReader with OggStreamReader in {elapsed:?}");
+
+	let start = Instant::now();
+	let mut reader = OggBufReader::new(BufReader::new(Cursor::new(&stream)));
+	let mut num_packets = 0usize;
+	while reader.get_packet().unwrap().is_some() {
+		num_packets += 1;
+	}
+	let elapsed = start.elapsed();
+	println!("Read {num_packets} small packets from a BufReader with OggBufReader in {elapsed:?}");
+
+	let mut large_pages = Vec::<u8>::new();
+	let mut builder = PacketBuilder::new(0x12345678, 0);
+	for i in 0..100u64 {
+		large_pages.extend(mux_pages(builder.build(&[i as u8; OggPacket::MAX_PAYLOAD - 1], i, i == 99)));
+	}
+	for read_size in [27, 2048] {
+		let start = Instant::now();
+		let mut reader = CountingReader{inner: Cursor::new(&large_pages), reads: 0};
+		let num_packets = read_with_drain(&mut reader, read_size);
+		let elapsed = start.elapsed();
+		println!("Read {num_packets} max size packets with the read size {read_size} with the drain() baseline in {elapsed:?}, {} read calls", reader.reads);
+
+		let start = Instant::now();
+		let mut reader = OggStreamReader::new(CountingReader{inner: Cursor::new(&large_pages), reads: 0});
+		reader.set_read_size(read_size);
+		let mut num_packets = 0usize;
+		while reader.get_packet().unwrap().is_some() {
+			num_packets += 1;
+		}
+		let elapsed = start.elapsed();
+		println!("Read {num_packets} max size packets with the read size {read_size} with the offset cache in {elapsed:?}, {} read calls", reader.reader.reads);
+	}
+}
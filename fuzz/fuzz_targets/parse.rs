@@ -0,0 +1,20 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use libfuzzer_sys::fuzz_target;
+use ogg::*;
+
+// None of the parsers should panic on arbitrary bytes, the errors are expected.
+fuzz_target!(|data: &[u8]| {
+	let _ = OggPacket::get_length(data);
+	let _ = OggPacket::from_bytes(data, &mut 0);
+	let _ = OggPacket::from_bytes_lenient(data, &mut 0);
+	let _ = OggPacket::from_bytes_with_limits(data, &mut 0, ParseLimits{max_page_size: 4096});
+	let _ = OggPacket::from_cursor(&mut Cursor::new(data.to_vec()));
+	let _ = OggPacket::scan_pages_checked(data);
+
+	let mut oggreader = OggStreamReader::new(Cursor::new(data));
+	oggreader.set_strict(true);
+	while let Ok(Some(_)) = oggreader.get_logical_packet() {}
+});
@@ -0,0 +1,388 @@
+use std::{
+	io,
+	fmt::{self, Debug, Formatter},
+	pin::Pin,
+	future::poll_fn,
+	task::{Context, Poll, ready},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+use futures_core::Stream;
+
+use crate::{OggError, OggPacket, OggPacketType, PageInfo, PageFlags, Pager};
+
+/// * An async ogg packet reader, the counterpart of `OggStreamReader` for `tokio`. The packets beginning with `OggS` are parsed unless `set_capture_pattern()` changes it.
+#[derive(Debug)]
+pub struct AsyncOggStreamReader<R>
+where
+	R: AsyncRead + Unpin {
+	/// * The reader
+	pub reader: R,
+
+	/// * If an EOS is encountered, this field is set to true
+	e_o_s: bool,
+
+	/// * If encountered EOF, this field is set to true
+	e_o_f: bool,
+
+	/// * The cached bytes for next read
+	cached_bytes: Vec<u8>,
+
+	/// * Where the unread bytes begin in `cached_bytes`, the consumed bytes are only removed when refilling the cache.
+	cached_offset: usize,
+
+	/// * The capture pattern of the packets
+	capture_pattern: [u8; 4],
+}
+
+impl<R> AsyncOggStreamReader<R>
+where
+	R: AsyncRead + Unpin {
+	const READ_SIZE: usize = 2048;
+
+	pub fn new(reader: R) -> Self {
+		Self {
+			reader,
+			e_o_s: false,
+			e_o_f: false,
+			cached_bytes: Vec::new(),
+			cached_offset: 0,
+			capture_pattern: OggPacket::CAPTURE_PATTERN,
+		}
+	}
+
+	/// * Set the capture pattern of the packets, the default is `OggS`, like `OggStreamReader::set_capture_pattern()`.
+	pub fn set_capture_pattern(&mut self, capture_pattern: [u8; 4]) {
+		self.capture_pattern = capture_pattern;
+	}
+
+	/// * Poll for the next packet, this is what `get_packet()` and the `Stream` implementation use.
+	/// * Like `OggStreamReader`, the stream ending in the middle of a packet is `OggError::Truncated`, and a corrupt packet is skipped after its `OggError::ChecksumMismatch` is returned.
+	pub fn poll_get_packet(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Option<OggPacket>>> {
+		loop {
+			let mut packet_length = 0usize;
+			match OggPacket::parse_with(&self.cached_bytes[self.cached_offset..], &mut packet_length, false, self.capture_pattern) {
+				Ok(packet) => {
+					self.e_o_s = packet.is_last_page();
+					self.cached_offset += packet_length;
+					return Poll::Ready(Ok(Some(packet)));
+				}
+				Err(OggError::UnexpectedEof{got, needed}) => { // Not enough bytes for an Ogg packet
+					if self.e_o_f {
+						if got == 0 {
+							return Poll::Ready(Ok(None));
+						} else {
+							return Poll::Ready(Err(OggError::Truncated{got, needed}.into()));
+						}
+					}
+					if self.e_o_s && got == 0 {
+						return Poll::Ready(Ok(None));
+					}
+					let to_read = needed.saturating_sub(got).max(Self::READ_SIZE);
+					let mut buf = vec![0u8; to_read];
+					let mut read_buf = ReadBuf::new(&mut buf);
+					ready!(Pin::new(&mut self.reader).poll_read(cx, &mut read_buf))?;
+					let read = read_buf.filled();
+					if read.is_empty() {
+						self.e_o_f = true;
+					}
+					// Only compact the cache when refilling it, the partial packet at the end is moved to the beginning.
+					self.cached_bytes.drain(..self.cached_offset);
+					self.cached_offset = 0;
+					self.cached_bytes.extend(read);
+				}
+				Err(e @ OggError::ChecksumMismatch{..}) => {
+					// Skip the corrupt packet, so the next call continues from the packet after it.
+					self.cached_offset += packet_length;
+					return Poll::Ready(Err(e.into()));
+				}
+				Err(e) => return Poll::Ready(Err(e.into())),
+			}
+		}
+	}
+
+	pub async fn get_packet(&mut self) -> io::Result<Option<OggPacket>> {
+		poll_fn(|cx|self.poll_get_packet(cx)).await
+	}
+
+	pub fn is_eos(&self) -> bool {
+		self.e_o_s
+	}
+
+	pub fn is_eof(&self) -> bool {
+		self.e_o_f
+	}
+}
+
+impl<R> Stream for AsyncOggStreamReader<R>
+where
+	R: AsyncRead + Unpin {
+	type Item = io::Result<OggPacket>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		self.get_mut().poll_get_packet(cx).map(|r|r.transpose())
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_async_reader() {
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let (expected, _) = OggPacket::from_cursor(&mut io::Cursor::new(test_ogg.clone()));
+	let mut reader = AsyncOggStreamReader::new(&test_ogg[..]);
+	for packet in expected {
+		let read = reader.get_packet().await.unwrap().unwrap();
+		assert_eq!(read.packet_index, packet.packet_index);
+		assert_eq!(read.data, packet.data);
+	}
+	assert!(reader.is_eos());
+	assert!(reader.get_packet().await.unwrap().is_none());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_async_reader_truncated() {
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let mut reader = AsyncOggStreamReader::new(&test_ogg[..test_ogg.len() - 1]);
+	for _ in 0..4 {
+		reader.get_packet().await.unwrap().unwrap();
+	}
+	let e = reader.get_packet().await.unwrap_err();
+	assert!(matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::Truncated{..})));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_async_reader_capture_pattern() {
+	let mut sink = Vec::new();
+	for i in 0..3 {
+		let mut packet = OggPacket::new(0x12345678, OggPacketType::Continuation, i);
+		packet.write(&[i as u8; 1000]);
+		sink.extend(packet.to_bytes_with_capture_pattern(*b"Test"));
+	}
+	// Corrupt the data of the second packet, it's skipped after the error.
+	let second = sink.len() / 3;
+	sink[second + 100] ^= 1;
+	let mut reader = AsyncOggStreamReader::new(&sink[..]);
+	let e = reader.get_packet().await.unwrap_err();
+	assert!(matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::BadCapturePattern(_))));
+	let mut reader = AsyncOggStreamReader::new(&sink[..]);
+	reader.set_capture_pattern(*b"Test");
+	assert_eq!(reader.get_packet().await.unwrap().unwrap().packet_index, 0);
+	let e = reader.get_packet().await.unwrap_err();
+	assert!(matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::ChecksumMismatch{..})));
+	assert_eq!(reader.get_packet().await.unwrap().unwrap().packet_index, 2);
+	assert!(reader.get_packet().await.unwrap().is_none());
+}
+
+/// * The callback of `AsyncOggStreamWriter` called after each packet is written, the `Send` counterpart of `OnPageWritten`
+pub type AsyncOnPageWritten = Box<dyn FnMut(&PageInfo) + Send>;
+
+/// * An async ogg packets writer sink, the counterpart of `OggStreamWriter` for `tokio`
+/// * Since `Drop` can't be async, call `finish().await` to write the EOS packet. Dropping the writer without finishing won't flush anything.
+pub struct AsyncOggStreamWriter<W>
+where
+	W: AsyncWrite + Unpin {
+	/// * The writer, when a packet is full or you want to seal the packet, the packet is flushed in the writer
+	pub writer: W,
+
+	/// * The unique stream ID for a whole stream. Programs use the stream ID to identify which packet is for which stream.
+	pub stream_id: u32,
+
+	/// * The packet index.
+	pub packet_index: u32,
+
+	/// * The current packet, ready to be written.
+	pub cur_packet: OggPacket,
+
+	/// * The granule position is for the programmers to reference it for some purpose.
+	pub granule_position: u64,
+
+	/// * When the `cur_packet` is full, the `on_seal()` closure will be called for updating the granule position.
+	/// * If the closure returns an error, `write_all()` returns it and the packet isn't sealed.
+	pub on_seal: Box<dyn FnMut(usize) -> io::Result<u64> + Send>,
+
+	/// * How many bytes were written into this stream.
+	pub bytes_written: u64,
+
+	/// * The callback called after each packet is written to the sink
+	on_page_written: Option<AsyncOnPageWritten>,
+
+	/// * The max data size of a packet
+	max_page_payload: usize,
+
+	/// * The capture pattern of the packets
+	capture_pattern: [u8; 4],
+
+	/// * If any packet was sealed
+	has_sealed: bool,
+
+	/// * If the EOS packet was written
+	ended: bool,
+}
+
+impl<W> AsyncOggStreamWriter<W>
+where
+	W: AsyncWrite + Unpin {
+	pub fn new(writer: W, stream_id: u32) -> Self {
+		Self {
+			writer,
+			stream_id,
+			packet_index: 0,
+			cur_packet: OggPacket::new(stream_id, OggPacketType::BeginOfStream, 0),
+			granule_position: 0,
+			on_seal: Box::new(|i|Ok(i as u64)),
+			bytes_written: 0,
+			on_page_written: None,
+			max_page_payload: OggPacket::MAX_PAYLOAD,
+			capture_pattern: *b"OggS",
+			has_sealed: false,
+			ended: false,
+		}
+	}
+
+	/// * Borrow the paging state shared with `OggStreamWriter`
+	fn pager(&mut self) -> Pager<'_> {
+		Pager {
+			stream_id: self.stream_id,
+			packet_index: &mut self.packet_index,
+			cur_packet: &mut self.cur_packet,
+			granule_position: &mut self.granule_position,
+			has_sealed: &mut self.has_sealed,
+			ended: &mut self.ended,
+			max_page_payload: self.max_page_payload,
+			capture_pattern: self.capture_pattern,
+		}
+	}
+
+	/// * If the current packet was marked as EOS or the EOS packet was written
+	pub fn is_ended(&self) -> bool {
+		self.ended || self.cur_packet.flags().contains(PageFlags::END_OF_STREAM)
+	}
+
+	/// * Set a callback for `write_all()` when it seals the packet, the callback helps with updating the granule position
+	pub fn set_on_seal_callback(&mut self, mut on_seal: Box<dyn FnMut(usize) -> u64 + Send>) {
+		self.on_seal = Box::new(move |size|Ok(on_seal(size)));
+	}
+
+	/// * Set a callback which could fail, its error is returned by the `write_all()` call which seals the packet
+	pub fn set_try_on_seal_callback(&mut self, on_seal: Box<dyn FnMut(usize) -> io::Result<u64> + Send>) {
+		self.on_seal = on_seal;
+	}
+
+	/// * Set a callback which is called after each packet is written to the sink, like `OggStreamWriter::set_on_page_written_callback()`.
+	pub fn set_on_page_written_callback(&mut self, on_page_written: AsyncOnPageWritten) {
+		self.on_page_written = Some(on_page_written);
+	}
+
+	/// * Set the max data size of a packet, it's clamped between 255 and `OggPacket::MAX_PAYLOAD` like `OggStreamWriter::set_max_page_payload()`.
+	pub fn set_max_page_payload(&mut self, max_page_payload: usize) {
+		self.max_page_payload = max_page_payload.clamp(255, OggPacket::MAX_PAYLOAD);
+	}
+
+	/// * Get the max data size of a packet
+	pub fn get_max_page_payload(&self) -> usize {
+		self.max_page_payload
+	}
+
+	/// * Set the capture pattern of the packets, the default is `OggS`.
+	pub fn set_capture_pattern(&mut self, capture_pattern: [u8; 4]) {
+		self.capture_pattern = capture_pattern;
+	}
+
+	/// * Write the data into the stream, full packets are sealed and written to the sink.
+	/// * After the EOS packet, it returns `OggError::WriteAfterEos`.
+	pub async fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+		self.pager().check_not_ended()?;
+		let mut buf = buf;
+		while !buf.is_empty() {
+			let written = self.pager().fill(buf);
+			if written == 0 {
+				let granule_position = (self.on_seal)(self.cur_packet.get_inner_data_size())?;
+				self.seal_packet(granule_position, false).await?;
+			}
+			buf = &buf[written..];
+		}
+		Ok(())
+	}
+
+	/// * Save the current packet and write it to the sink, then create a new packet for writing.
+	/// * After the EOS packet, it returns `OggError::WriteAfterEos`.
+	pub async fn seal_packet(&mut self, granule_position: u64, is_end_of_stream: bool) -> io::Result<()> {
+		let (bytes, info) = self.pager().seal(granule_position, is_end_of_stream)?;
+		self.writer.write_all(&bytes).await?;
+		self.bytes_written += info.payload_len as u64;
+		if let Some(on_page_written) = self.on_page_written.as_mut() {
+			on_page_written(&info);
+		}
+		Ok(())
+	}
+
+	/// * Seal the current packet as the EOS packet, flush the sink and give it back.
+	pub async fn finish(mut self) -> io::Result<W> {
+		self.seal_packet(self.granule_position, true).await?;
+		self.writer.flush().await?;
+		Ok(self.writer)
+	}
+}
+
+impl<W> Debug for AsyncOggStreamWriter<W>
+where
+	W: AsyncWrite + Unpin + Debug {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		f.debug_struct(&format!("AsyncOggStreamWriter<{}>", std::any::type_name::<W>()))
+		.field("writer", &self.writer)
+		.field("stream_id", &format_args!("0x{:08x}", self.stream_id))
+		.field("packet_index", &self.packet_index)
+		.field("cur_packet", &self.cur_packet)
+		.field("granule_position", &self.granule_position)
+		.field("on_seal", &format_args!("<closure>"))
+		.field("bytes_written", &self.bytes_written)
+		.field("on_page_written", &format_args!("{}", if self.on_page_written.is_some() {"<closure>"} else {"None"}))
+		.field("max_page_payload", &self.max_page_payload)
+		.finish()
+	}
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_async_writer() {
+	let mut writer = AsyncOggStreamWriter::new(Vec::<u8>::new(), 0x12345678);
+	writer.write_all(&[1u8; 100000]).await.unwrap();
+	writer.seal_packet(100000, false).await.unwrap();
+	writer.write_all(b"The end").await.unwrap();
+	let sink = writer.finish().await.unwrap();
+	let (packets, error) = OggPacket::from_cursor(&mut io::Cursor::new(sink));
+	assert_eq!(error, None);
+	assert_eq!(packets.len(), 3);
+	assert_eq!(packets[0].packet_type, OggPacketType::BeginOfStream);
+	assert_eq!(packets[1].granule_position, 100000);
+	assert_eq!(packets[2].packet_type, OggPacketType::EndOfStream);
+	assert_eq!(packets[2].get_inner_data(), b"The end");
+	let data: Vec<u8> = packets.into_iter().flat_map(|p|p.into_inner()).collect();
+	assert_eq!(data.len(), 100007);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_async_writer_paging() {
+	let mut writer = AsyncOggStreamWriter::new(Vec::<u8>::new(), 0x12345678);
+	writer.set_max_page_payload(255 * 4);
+	writer.set_capture_pattern(*b"Test");
+	let pages = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+	let pages_written = pages.clone();
+	writer.set_on_page_written_callback(Box::new(move |info|pages_written.lock().unwrap().push(*info)));
+	writer.write_all(&[1u8; 2000]).await.unwrap();
+	writer.seal_packet(2000, true).await.unwrap();
+	assert!(writer.is_ended());
+	let error = writer.write_all(b"More").await.unwrap_err();
+	assert!(matches!(error.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::WriteAfterEos{..})));
+	assert_eq!(writer.bytes_written, 2000);
+	let pages = pages.lock().unwrap().clone();
+	assert_eq!(pages.iter().map(|info|info.payload_len).collect::<Vec<_>>(), [1020, 980]);
+	assert!(pages[1].is_eos);
+	let mut reader = crate::OggStreamReader::new(io::Cursor::new(writer.writer));
+	reader.set_capture_pattern(*b"Test");
+	assert_eq!(reader.get_packet().unwrap().unwrap().get_inner_data_size(), 1020);
+	assert!(reader.get_packet().unwrap().unwrap().is_last_page());
+}
@@ -0,0 +1,179 @@
+use crate::theora_granule_to_frame;
+
+/// * The codecs that could be encapsulated in an Ogg stream, along with the parameters needed to interpret the granule positions.
+/// * The parameters are zero if the first packet is too short to contain them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+	/// * The first packet begins with `\x01vorbis`, the granule position is the number of samples at the sample rate
+	Vorbis {
+		sample_rate: u32,
+	},
+
+	/// * The first packet begins with `OpusHead`, the granule position is always at 48 kHz, including the pre-skip samples
+	Opus {
+		pre_skip: u16,
+	},
+
+	/// * The first packet begins with `\x7fFLAC`, the granule position is the number of samples at the sample rate
+	Flac {
+		sample_rate: u32,
+	},
+
+	/// * The first packet begins with `Speex   `, the granule position is the number of samples at the sample rate
+	Speex {
+		sample_rate: u32,
+	},
+
+	/// * The first packet begins with `\x80theora`, the granule position encodes the last keyframe and the frames since it
+	Theora {
+		frame_rate_numerator: u32,
+		frame_rate_denominator: u32,
+		granule_shift: u8,
+	},
+
+	/// * The first packet begins with `fishead\0`
+	Skeleton,
+
+	/// * Not recognized
+	Unknown,
+}
+
+fn read_u32_le(packet: &[u8], offset: usize) -> u32 {
+	packet.get(offset..offset + 4).map_or(0, |b|u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn read_u32_be(packet: &[u8], offset: usize) -> u32 {
+	packet.get(offset..offset + 4).map_or(0, |b|u32::from_be_bytes(b.try_into().unwrap()))
+}
+
+/// * Detect the codec by the magic signature of the first logical packet of a stream.
+pub fn detect_codec(first_packet: &[u8]) -> Codec {
+	let p = first_packet;
+	if p.starts_with(b"\x01vorbis") {
+		Codec::Vorbis {
+			sample_rate: read_u32_le(p, 12),
+		}
+	} else if p.starts_with(b"OpusHead") {
+		Codec::Opus {
+			pre_skip: p.get(10..12).map_or(0, |b|u16::from_le_bytes([b[0], b[1]])),
+		}
+	} else if p.starts_with(b"\x7fFLAC") {
+		Codec::Flac {
+			sample_rate: read_u32_be(p, 27) >> 12,
+		}
+	} else if p.starts_with(b"Speex   ") {
+		Codec::Speex {
+			sample_rate: read_u32_le(p, 36),
+		}
+	} else if p.starts_with(b"\x80theora") {
+		Codec::Theora {
+			frame_rate_numerator: read_u32_be(p, 22),
+			frame_rate_denominator: read_u32_be(p, 26),
+			granule_shift: p.get(40..42).map_or(0, |b|((b[0] & 0x03) << 3) | (b[1] >> 5)),
+		}
+	} else if p.starts_with(b"fishead\0") {
+		Codec::Skeleton
+	} else {
+		Codec::Unknown
+	}
+}
+
+/// * Convert a granule position to the time in seconds.
+/// * For Opus, the pre-skip is subtracted, the time during the pre-skip is clamped to zero.
+/// * For the codecs without time information or the rate is unknown, returns `f64::NAN`.
+pub fn granule_to_seconds(codec: &Codec, granule: u64) -> f64 {
+	match *codec {
+		Codec::Vorbis{sample_rate} | Codec::Flac{sample_rate} | Codec::Speex{sample_rate} if sample_rate != 0 => {
+			granule as f64 / sample_rate as f64
+		}
+		Codec::Opus{pre_skip} => {
+			granule.saturating_sub(pre_skip as u64) as f64 / 48000.0
+		}
+		Codec::Theora{frame_rate_numerator, frame_rate_denominator, granule_shift} if frame_rate_numerator != 0 => {
+			let frames = theora_granule_to_frame(granule, granule_shift);
+			frames as f64 * frame_rate_denominator as f64 / frame_rate_numerator as f64
+		}
+		_ => f64::NAN,
+	}
+}
+
+/// * Convert the time in seconds to a granule position, the inverse of `granule_to_seconds()`.
+/// * For Opus, the pre-skip is added. For Theora, the granule position of the frame as a keyframe is returned.
+/// * For the codecs without time information or the rate is unknown, returns zero.
+pub fn seconds_to_granule(codec: &Codec, seconds: f64) -> u64 {
+	let seconds = seconds.max(0.0);
+	match *codec {
+		Codec::Vorbis{sample_rate} | Codec::Flac{sample_rate} | Codec::Speex{sample_rate} => {
+			(seconds * sample_rate as f64).round() as u64
+		}
+		Codec::Opus{pre_skip} => {
+			(seconds * 48000.0).round() as u64 + pre_skip as u64
+		}
+		Codec::Theora{frame_rate_numerator, frame_rate_denominator, granule_shift} if frame_rate_denominator != 0 => {
+			let frames = (seconds * frame_rate_numerator as f64 / frame_rate_denominator as f64).round() as u64;
+			frames << granule_shift
+		}
+		_ => 0,
+	}
+}
+
+/// * Distribute the granule positions over the packets completed in a page, the page only carries the granule position at the end of its last completed packet.
+/// * The span from the granule position of the previous page is divided evenly, it's only an approximation since the packets may decode to different numbers of samples.
+/// * For Vorbis or Opus, when the sample count of each packet is known, use `packet_granules_from_samples()` instead.
+pub fn packet_granules(prev_page_granule: u64, this_page_granule: u64, num_completed_packets: usize) -> Vec<u64> {
+	let span = this_page_granule.saturating_sub(prev_page_granule) as u128;
+	let n = num_completed_packets as u128;
+	(1..=n).map(|i|prev_page_granule + (span * i / n) as u64).collect()
+}
+
+/// * Calculate the granule position of each packet completed in a page from their sample counts, counting backwards from the granule position of the page.
+/// * The last packet gets `this_page_granule`, each packet before it gets it minus the samples of the packets after it.
+pub fn packet_granules_from_samples(this_page_granule: u64, samples: &[u64]) -> Vec<u64> {
+	let mut granule = this_page_granule;
+	let mut ret: Vec<u64> = samples.iter().rev().map(|&s|{
+		let cur = granule;
+		granule = granule.saturating_sub(s);
+		cur
+	}).collect();
+	ret.reverse();
+	ret
+}
+
+#[test]
+fn test_detect_codec() {
+	let cases: [(&[u8], Codec); 8] = [
+		(b"\x01vorbis\0\0\0\0\x02\x44\xac\0\0", Codec::Vorbis{sample_rate: 44100}),
+		(b"OpusHead\x01\x02\x38\x01\x80\xbb\0\0\0\0\0", Codec::Opus{pre_skip: 312}),
+		(b"\x7fFLAC\x01\x00\x00\x01fLaC", Codec::Flac{sample_rate: 0}),
+		(b"Speex   1.2.1", Codec::Speex{sample_rate: 0}),
+		(b"\x80theora\x03\x02\x01", Codec::Theora{frame_rate_numerator: 0, frame_rate_denominator: 0, granule_shift: 0}),
+		(b"fishead\0\x03\0\0\0", Codec::Skeleton),
+		(b"\x01vorbi", Codec::Unknown),
+		(b"", Codec::Unknown),
+	];
+	for (packet, codec) in cases {
+		assert_eq!(detect_codec(packet), codec);
+	}
+}
+
+#[test]
+fn test_granule_to_seconds() {
+	let vorbis = Codec::Vorbis{sample_rate: 44100};
+	assert_eq!(granule_to_seconds(&vorbis, 441000), 10.0);
+	assert_eq!(seconds_to_granule(&vorbis, 10.0), 441000);
+
+	let opus = Codec::Opus{pre_skip: 312};
+	assert_eq!(granule_to_seconds(&opus, 48312), 1.0);
+	assert_eq!(granule_to_seconds(&opus, 100), 0.0);
+	assert_eq!(seconds_to_granule(&opus, 1.0), 48312);
+
+	assert!(granule_to_seconds(&Codec::Unknown, 1000).is_nan());
+}
+
+#[test]
+fn test_packet_granules() {
+	assert_eq!(packet_granules(1000, 4000, 3), vec![2000, 3000, 4000]);
+	assert_eq!(packet_granules(0, 10, 3), vec![3, 6, 10]);
+	assert_eq!(packet_granules(0, 10, 0), Vec::<u64>::new());
+	assert_eq!(packet_granules_from_samples(4000, &[960, 480, 960]), vec![2560, 3040, 4000]);
+}
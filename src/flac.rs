@@ -0,0 +1,118 @@
+use std::io::{self, ErrorKind};
+
+/// * The first header packet of a FLAC-in-Ogg stream, which carries the FLAC STREAMINFO block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlacOggHeader {
+	/// * The major version of the Ogg FLAC mapping, should be 1
+	pub major_version: u8,
+
+	/// * The minor version of the Ogg FLAC mapping
+	pub minor_version: u8,
+
+	/// * How many header packets follow this packet, zero means unknown
+	pub num_header_packets: u16,
+
+	/// * The minimum block size in samples
+	pub min_block_size: u16,
+
+	/// * The maximum block size in samples
+	pub max_block_size: u16,
+
+	/// * The minimum frame size in bytes, zero means unknown
+	pub min_frame_size: u32,
+
+	/// * The maximum frame size in bytes, zero means unknown
+	pub max_frame_size: u32,
+
+	/// * The sample rate in Hz
+	pub sample_rate: u32,
+
+	/// * The number of channels, from 1 to 8
+	pub channels: u8,
+
+	/// * Bits per sample, from 4 to 32
+	pub bits_per_sample: u8,
+
+	/// * Total samples per channel, zero means unknown
+	pub total_samples: u64,
+
+	/// * The MD5 of the unencoded audio data
+	pub md5: [u8; 16],
+}
+
+impl FlacOggHeader {
+	/// * The size of the header packet: 13 bytes of the mapping header, 4 bytes of the metadata block header and 34 bytes of the STREAMINFO
+	pub const SIZE: usize = 51;
+
+	/// * Parse the first packet of a FLAC-in-Ogg stream
+	pub fn parse(packet: &[u8]) -> io::Result<Self> {
+		if packet.len() < Self::SIZE {
+			return Err(io::Error::new(ErrorKind::UnexpectedEof, format!("The FLAC header packet is too small: {} < {}", packet.len(), Self::SIZE)));
+		}
+		if packet[0..5] != *b"\x7fFLAC" {
+			return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing FLAC header: expected `\\x7fFLAC`, got `{}`", String::from_utf8_lossy(&packet[0..5]))));
+		}
+		let major_version = packet[5];
+		if major_version != 1 {
+			return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing FLAC header: unsupported mapping version {major_version}.{}", packet[6])));
+		}
+		if packet[9..13] != *b"fLaC" {
+			return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing FLAC header: expected `fLaC`, got `{}`", String::from_utf8_lossy(&packet[9..13]))));
+		}
+		let block_type = packet[13] & 0x7F;
+		let block_length = u32::from_be_bytes([0, packet[14], packet[15], packet[16]]);
+		if block_type != 0 || block_length != 34 {
+			return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing FLAC header: expected STREAMINFO block of 34 bytes, got block type {block_type} of {block_length} bytes")));
+		}
+		let info = &packet[17..Self::SIZE];
+		let packed = u64::from_be_bytes(info[10..18].try_into().unwrap());
+		Ok(Self {
+			major_version,
+			minor_version: packet[6],
+			num_header_packets: u16::from_be_bytes([packet[7], packet[8]]),
+			min_block_size: u16::from_be_bytes([info[0], info[1]]),
+			max_block_size: u16::from_be_bytes([info[2], info[3]]),
+			min_frame_size: u32::from_be_bytes([0, info[4], info[5], info[6]]),
+			max_frame_size: u32::from_be_bytes([0, info[7], info[8], info[9]]),
+			sample_rate: (packed >> 44) as u32,
+			channels: ((packed >> 41) & 0x07) as u8 + 1,
+			bits_per_sample: ((packed >> 36) & 0x1F) as u8 + 1,
+			total_samples: packed & 0xF_FFFF_FFFF,
+			md5: info[18..34].try_into().unwrap(),
+		})
+	}
+
+	/// * The duration of the stream in seconds, `None` if the total samples is unknown
+	pub fn duration(&self) -> Option<f64> {
+		if self.total_samples == 0 || self.sample_rate == 0 {
+			None
+		} else {
+			Some(self.total_samples as f64 / self.sample_rate as f64)
+		}
+	}
+}
+
+#[test]
+fn test_flac_ogg_header() {
+	let mut packet = b"\x7fFLAC\x01\x00\x00\x02fLaC\x00\x00\x00\x22".to_vec();
+	packet.extend([0x10, 0x00, 0x10, 0x00]); // Block size 4096
+	packet.extend([0x00, 0x00, 0x10, 0x00, 0x20, 0x00]); // Frame size
+	let packed: u64 = (44100 << 44) | (1 << 41) | (15 << 36) | 441000;
+	packet.extend(packed.to_be_bytes());
+	packet.extend([0xAAu8; 16]);
+	let header = FlacOggHeader::parse(&packet).unwrap();
+	assert_eq!(header.major_version, 1);
+	assert_eq!(header.num_header_packets, 2);
+	assert_eq!(header.min_block_size, 4096);
+	assert_eq!(header.max_frame_size, 0x2000);
+	assert_eq!(header.sample_rate, 44100);
+	assert_eq!(header.channels, 2);
+	assert_eq!(header.bits_per_sample, 16);
+	assert_eq!(header.total_samples, 441000);
+	assert_eq!(header.duration(), Some(10.0));
+
+	packet[9] = b'f';
+	packet[10] = b'l';
+	assert!(FlacOggHeader::parse(&packet).is_err());
+	assert!(FlacOggHeader::parse(&packet[..50]).is_err());
+}
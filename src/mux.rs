@@ -0,0 +1,277 @@
+use std::{
+	collections::{VecDeque, hash_map::RandomState},
+	fmt::{self, Debug, Formatter},
+	hash::{BuildHasher, Hasher},
+	io::{self, ErrorKind, Write},
+	sync::atomic::{AtomicU64, Ordering},
+	time::SystemTime,
+};
+
+use crate::{Codec, OggPacket, PacketBuilder, PageFlags, granule_to_seconds};
+
+/// * Generate a random stream ID (serial number), Ogg stream IDs are conventionally random so that the streams of different files don't collide when they are muxed or chained.
+/// * It's seeded by the randomly keyed hasher of the standard library, the time, and a counter, so that the IDs differ in the same process and across processes. It never returns 0.
+/// * 0 is a valid stream ID, but it's the default of many tools, so it's more likely to collide with another stream.
+pub fn random_stream_id() -> u32 {
+	static COUNTER: AtomicU64 = AtomicU64::new(0);
+	let mut hasher = RandomState::new().build_hasher();
+	hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+	if let Ok(time) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+		hasher.write_u128(time.as_nanos());
+	}
+	let hash = hasher.finish();
+	match (hash ^ (hash >> 32)) as u32 {
+		0 => 1,
+		stream_id => stream_id,
+	}
+}
+
+/// * A logical stream of the muxer, with its packets waiting to be interleaved
+struct MuxStream {
+	codec: Codec,
+
+	/// * Splits the logical packets into packets and numbers them
+	builder: PacketBuilder,
+
+	/// * The packets waiting to be written, along with the time of the logical packet they belong to
+	pages: VecDeque<(f64, OggPacket)>,
+
+	/// * The granule position of the last logical packet, for the EOS packet
+	last_granule: u64,
+
+	/// * The time of the last logical packet, for the EOS packet
+	last_time: f64,
+
+	/// * If `end_stream()` was called, no more logical packets can be pushed
+	ended: bool,
+}
+
+/// * A muxer which interleaves multiple logical streams into one physical stream.
+/// * The BOS packets of all of the streams are written first, then the packets are interleaved by the time of their granule positions, so that the output is seek-friendly.
+/// * The packets are held until every stream has some packets to interleave, push the logical packets of the streams in about the same pace to keep the buffering low.
+///   Call `end_stream()` for a stream without more packets, e.g. a metadata-only stream, so that it doesn't hold back the other streams.
+/// * Each logical packet is split into its own packets by `PacketBuilder`, so the packets of the different streams can be interleaved at any logical packet boundary.
+/// * Call `finish()` to write the held packets and the EOS packets, dropping the muxer without finishing won't write them.
+pub struct OggMuxer<W>
+where
+	W: Write + Debug {
+	/// * The writer
+	pub writer: W,
+
+	/// * The streams in the order they were added
+	streams: Vec<(u32, MuxStream)>,
+
+	/// * If the BOS packets were written, no more streams can be added after it
+	bos_written: bool,
+}
+
+impl<W> OggMuxer<W>
+where
+	W: Write + Debug {
+	pub fn new(writer: W) -> Self {
+		Self {
+			writer,
+			streams: Vec::new(),
+			bos_written: false,
+		}
+	}
+
+	/// * Add a logical stream, the codec tells how to convert its granule positions to time for the interleaving.
+	/// * For the streams without time information (e.g. `Codec::Skeleton`, `Codec::Unknown`), their packets are written as soon as possible.
+	/// * All of the streams must be added before their BOS packets are written, which happens when every stream got its first logical packet.
+	/// * The stream IDs must be unique, otherwise the demuxer can't separate the streams, adding a stream with a used ID returns an error. See `add_random_stream()`.
+	pub fn add_stream(&mut self, stream_id: u32, codec: Codec) -> io::Result<()> {
+		if self.bos_written {
+			return Err(io::Error::new(ErrorKind::InvalidInput, format!("Can't add the stream 0x{stream_id:08x}: the BOS packets were already written")));
+		}
+		if self.streams.iter().any(|(id, _)|*id == stream_id) {
+			return Err(io::Error::new(ErrorKind::InvalidInput, format!("The stream 0x{stream_id:08x} was already added")));
+		}
+		self.streams.push((stream_id, MuxStream {
+			codec,
+			builder: PacketBuilder::new(stream_id, 0),
+			pages: VecDeque::new(),
+			last_granule: 0,
+			last_time: 0.0,
+			ended: false,
+		}));
+		Ok(())
+	}
+
+	/// * Add a logical stream with a random stream ID which isn't used by the other streams, returns its stream ID.
+	pub fn add_random_stream(&mut self, codec: Codec) -> io::Result<u32> {
+		let stream_id = loop {
+			let stream_id = random_stream_id();
+			if self.streams.iter().all(|(id, _)|*id != stream_id) {
+				break stream_id;
+			}
+		};
+		self.add_stream(stream_id, codec)?;
+		Ok(stream_id)
+	}
+
+	/// * Push a logical packet of a stream with the granule position at the end of it, header packets have the granule position 0.
+	/// * The first logical packet of each stream is its BOS packet, so it should be the identification header of the codec.
+	pub fn push_packet(&mut self, stream_id: u32, packet: &[u8], granule_position: u64) -> io::Result<()> {
+		let stream = match self.streams.iter_mut().find(|(id, _)|*id == stream_id) {
+			Some((_, stream)) if !stream.ended => stream,
+			Some(_) => return Err(io::Error::new(ErrorKind::InvalidInput, format!("The stream 0x{stream_id:08x} was already ended"))),
+			None => return Err(io::Error::new(ErrorKind::InvalidInput, format!("The stream 0x{stream_id:08x} wasn't added"))),
+		};
+		let time = granule_to_seconds(&stream.codec, granule_position);
+		let time = if time.is_nan() {0.0} else {time};
+		for page in stream.builder.build(packet, granule_position, false) {
+			stream.pages.push_back((time, page));
+		}
+		stream.last_granule = granule_position;
+		stream.last_time = time;
+		self.write_ready()
+	}
+
+	/// * End a stream, its last packet is marked as EOS. An ended stream no longer holds back the packets of the other streams.
+	/// * If all of the packets of the stream were written, an empty EOS packet is added. A stream without any logical packet gets an empty BOS and EOS packet.
+	pub fn end_stream(&mut self, stream_id: u32) -> io::Result<()> {
+		let stream = match self.streams.iter_mut().find(|(id, _)|*id == stream_id) {
+			Some((_, stream)) if !stream.ended => stream,
+			Some(_) => return Err(io::Error::new(ErrorKind::InvalidInput, format!("The stream 0x{stream_id:08x} was already ended"))),
+			None => return Err(io::Error::new(ErrorKind::InvalidInput, format!("The stream 0x{stream_id:08x} wasn't added"))),
+		};
+		match stream.pages.back_mut() {
+			Some((_, page)) => {
+				page.set_flags(page.flags() | PageFlags::END_OF_STREAM);
+				page.recompute_checksum();
+			}
+			None => {
+				let flags = if stream.builder.packet_index == 0 {PageFlags::BEGIN_OF_STREAM | PageFlags::END_OF_STREAM} else {PageFlags::END_OF_STREAM};
+				let mut page = OggPacket::new_with_flags(stream_id, flags, stream.builder.packet_index);
+				page.granule_position = stream.last_granule;
+				page.recompute_checksum();
+				stream.builder.packet_index += 1;
+				stream.pages.push_back((stream.last_time, page));
+			}
+		}
+		stream.ended = true;
+		self.write_ready()
+	}
+
+	/// * Write the BOS packets if every stream has one, then the packets which are the earliest of all of the streams.
+	/// * A packet is only written when every stream which isn't ended has packets held, otherwise a later logical packet of an empty stream may be earlier than it.
+	fn write_ready(&mut self) -> io::Result<()> {
+		if !self.bos_written {
+			if self.streams.iter().any(|(_, stream)|stream.pages.is_empty()) {
+				return Ok(());
+			}
+			for (_, stream) in self.streams.iter_mut() {
+				let (_, page) = stream.pages.pop_front().unwrap();
+				page.write_to(&mut self.writer)?;
+			}
+			self.bos_written = true;
+		}
+		while self.streams.iter().all(|(_, stream)|stream.ended || !stream.pages.is_empty()) && self.write_earliest()? {}
+		Ok(())
+	}
+
+	/// * Write the earliest packet of all of the streams, returns false if there are no packets held.
+	fn write_earliest(&mut self) -> io::Result<bool> {
+		let earliest = self.streams.iter_mut()
+			.filter(|(_, stream)|!stream.pages.is_empty())
+			.min_by(|(_, a), (_, b)|a.pages[0].0.total_cmp(&b.pages[0].0));
+		match earliest {
+			Some((_, stream)) => {
+				let (_, page) = stream.pages.pop_front().unwrap();
+				page.write_to(&mut self.writer)?;
+				Ok(true)
+			}
+			None => Ok(false),
+		}
+	}
+
+	/// * End all of the streams which aren't ended, write all of the held packets in the order of time, then flush the writer and give it back.
+	pub fn finish(mut self) -> io::Result<W> {
+		let stream_ids: Vec<u32> = self.streams.iter().filter(|(_, stream)|!stream.ended).map(|(id, _)|*id).collect();
+		for stream_id in stream_ids {
+			self.end_stream(stream_id)?;
+		}
+		while self.write_earliest()? {}
+		self.writer.flush()?;
+		Ok(self.writer)
+	}
+}
+
+impl<W> Debug for OggMuxer<W>
+where
+	W: Write + Debug {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		f.debug_struct(&format!("OggMuxer<{}>", std::any::type_name::<W>()))
+		.field("writer", &self.writer)
+		.field("streams", &format_args!("{:08x?}", self.streams.iter().map(|(id, _)|*id).collect::<Vec<u32>>()))
+		.field("bos_written", &self.bos_written)
+		.finish()
+	}
+}
+
+#[test]
+fn test_muxer() {
+	use std::{collections::HashMap, io::Cursor};
+	use crate::validate_stream;
+	let audio = Codec::Vorbis{sample_rate: 48000};
+	let video = Codec::Theora{frame_rate_numerator: 25, frame_rate_denominator: 1, granule_shift: 0};
+	let mut muxer = OggMuxer::new(Vec::<u8>::new());
+	muxer.add_stream(1, audio).unwrap();
+	muxer.add_stream(2, video).unwrap();
+	muxer.add_stream(3, Codec::Unknown).unwrap();
+	assert_eq!(muxer.add_stream(3, Codec::Unknown).unwrap_err().kind(), ErrorKind::InvalidInput);
+	muxer.push_packet(3, b"metadata", 0).unwrap();
+	muxer.end_stream(3).unwrap();
+	muxer.push_packet(1, b"\x01vorbis header", 0).unwrap();
+	// The audio is pushed ahead of the video, the muxer interleaves them by time.
+	for i in 1..=50u64 {
+		muxer.push_packet(1, &[1u8; 100], i * 4800).unwrap();
+	}
+	assert!(muxer.writer.is_empty());
+	muxer.push_packet(2, b"\x80theora header", 0).unwrap();
+	assert!(!muxer.writer.is_empty());
+	assert_eq!(muxer.add_stream(4, Codec::Unknown).unwrap_err().kind(), ErrorKind::InvalidInput);
+	for i in 1..=125u64 {
+		muxer.push_packet(2, &[2u8; 100], i).unwrap();
+	}
+	assert!(muxer.push_packet(3, b"", 0).is_err());
+	assert!(muxer.push_packet(5, b"", 0).is_err());
+	let sink = muxer.finish().unwrap();
+
+	let (packets, consumed) = OggPacket::parse_all(&sink);
+	assert_eq!(consumed, sink.len());
+	assert!(packets[0..3].iter().all(|p|p.is_first_page()));
+	assert!(packets[3..].iter().all(|p|!p.is_first_page()));
+	assert!(packets[2].is_last_page());
+	let codecs = HashMap::from([(1, audio), (2, video)]);
+	let times: Vec<f64> = packets[3..].iter()
+		.filter(|p|p.granule_position != u64::MAX)
+		.map(|p|granule_to_seconds(&codecs[&p.stream_id], p.granule_position))
+		.collect();
+	assert!(times.windows(2).all(|w|w[0] <= w[1]));
+	for stream_id in 1..=3u32 {
+		let pages: Vec<&OggPacket> = packets.iter().filter(|p|p.stream_id == stream_id).collect();
+		assert!(pages.last().unwrap().is_last_page());
+		assert_eq!(pages.iter().filter(|p|p.is_last_page()).count(), 1);
+		assert!(pages.iter().enumerate().all(|(i, p)|p.packet_index == i as u32));
+	}
+
+	let num_packets: HashMap<u32, usize> = (1..=3u32).map(|id|(id, packets.iter().filter(|p|p.stream_id == id && !p.data.is_empty()).count())).collect();
+	assert_eq!(num_packets, HashMap::from([(1, 51), (2, 126), (3, 1)]));
+	assert!(validate_stream(Cursor::new(sink)).is_valid());
+}
+
+#[test]
+fn test_duplicate_stream_id() {
+	use std::collections::HashSet;
+	let mut muxer = OggMuxer::new(Vec::<u8>::new());
+	muxer.add_stream(0, Codec::Unknown).unwrap();
+	let e = muxer.add_stream(0, Codec::Unknown).unwrap_err();
+	assert_eq!(e.kind(), ErrorKind::InvalidInput);
+	let stream_ids: HashSet<u32> = (0..100).map(|_|muxer.add_random_stream(Codec::Unknown).unwrap()).collect();
+	assert_eq!(stream_ids.len(), 100);
+	assert!(!stream_ids.contains(&0));
+	assert_eq!(muxer.streams.len(), 101);
+	assert_ne!(random_stream_id(), random_stream_id());
+}
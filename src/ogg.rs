@@ -1,13 +1,201 @@
 #![allow(dead_code)]
 
 use std::{
-	cmp::max,
-	io::{self, Read, Write, Cursor, ErrorKind},
+	collections::{HashMap, HashSet, VecDeque},
+	io::{self, Read, BufRead, Write, Cursor, ErrorKind},
 	mem,
 	fmt::{self, Debug, Formatter}
 };
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+mod codec;
+mod flac;
+mod mux;
+mod opus;
+mod parser;
+mod seek;
+mod skeleton;
+mod speex;
+mod theora;
+mod transcode;
+mod validate;
+mod vorbis;
+pub use codec::*;
+pub use flac::*;
+pub use mux::*;
+pub use opus::*;
+pub use parser::*;
+pub use seek::*;
+pub use skeleton::*;
+pub use speex::*;
+pub use theora::*;
+pub use transcode::*;
+pub use validate::*;
+pub use vorbis::*;
+
+#[cfg(feature = "testing")]
+mod testing;
+#[cfg(feature = "testing")]
+pub use testing::*;
+
+#[cfg(feature = "tokio")]
+mod async_io;
+#[cfg(feature = "tokio")]
+pub use async_io::*;
+
+/// * The errors that could occur while parsing an Ogg packet
+#[derive(Debug, Clone, PartialEq)]
+pub enum OggError {
+	/// * Not enough bytes for the whole packet, `needed` is how many bytes it takes at least
+	UnexpectedEof {
+		got: usize,
+		needed: usize,
+	},
+
+	/// * The capture pattern isn't `OggS`
+	BadCapturePattern([u8; 4]),
+
+	/// * The version isn't zero
+	BadVersion(u8),
+
+	/// * The reserved bits 3 to 7 of the header type are set, only reported in the strict mode of `OggStreamReader`
+	BadPacketType(u8),
+
+	/// * The checksum field doesn't match the calculated checksum
+	/// * `offset` is the byte offset where the packet begins: in the input of the readers, from the beginning of the cursor for `OggPacket::from_cursor()`, or in the given bytes otherwise.
+	ChecksumMismatch {
+		expected: u32,
+		got: u32,
+		offset: u64,
+	},
+
+	/// * The segment table has more than 255 entries
+	TooManySegments(usize),
+
+	/// * The size of the data doesn't equal the sum of the segment table
+	DataSizeMismatch {
+		expected: usize,
+		got: usize,
+	},
+
+	/// * The packet index jumped, some packets of the stream were lost
+	SequenceGap {
+		stream_id: u32,
+		expected: u32,
+		got: u32,
+	},
+
+	/// * Strict mode: the packet index didn't increase
+	SequenceRegression {
+		stream_id: u32,
+		prev: u32,
+		got: u32,
+	},
+
+	/// * Strict mode: the segment table doesn't terminate a packet where it must, e.g. the last segment of the EOS packet is 255,
+	///   or the granule position is set but no packet finishes in the packet.
+	UnterminatedPacket {
+		stream_id: u32,
+		packet_index: u32,
+	},
+
+	/// * Strict mode: the first packet of a stream, or the first packet after its EOS packet, isn't a BOS packet
+	MissingBos {
+		stream_id: u32,
+		packet_index: u32,
+	},
+
+	/// * Strict mode: a BOS packet appeared after the other packets of a stream which didn't reach its EOS packet
+	BosNotFirst {
+		stream_id: u32,
+		packet_index: u32,
+	},
+
+	/// * The granule position of a stream decreased
+	GranuleRegression {
+		stream_id: u32,
+		prev: u64,
+		got: u64,
+	},
+
+	/// * The input ended in the middle of a packet, `got` is how many bytes of it were read
+	Truncated {
+		got: usize,
+		needed: usize,
+	},
+
+	/// * The reader needs to buffer more bytes than the limit to get a packet, or the packet is larger than `ParseLimits::max_page_size`
+	BufferOverflow {
+		limit: usize,
+		needed: usize,
+	},
+
+	/// * The bytes converted to a single packet have more bytes after the packet, `got` is the size of the bytes
+	TrailingBytes {
+		packet_size: usize,
+		got: usize,
+	},
+
+	/// * `OggStreamWriter` was asked to write after the current packet was marked as EOS or the EOS packet was written
+	WriteAfterEos {
+		stream_id: u32,
+	},
+
+	/// * The segment table claims more data than a packet can have, reading more bytes would never complete the packet
+	MalformedSegmentTable {
+		num_segments: usize,
+		data_length: usize,
+	},
+}
+
+impl fmt::Display for OggError {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			Self::UnexpectedEof{got, needed} => write!(f, "The given data size is too small: {got} < {needed}"),
+			Self::BadCapturePattern(pattern) => write!(f, "While parsing Ogg packet: unexpected capture pattern `{}`", String::from_utf8_lossy(pattern)),
+			Self::BadVersion(version) => write!(f, "While parsing Ogg packet: invalid `version` = {version} (should be zero)"),
+			Self::BadPacketType(packet_type) => write!(f, "While parsing Ogg packet: invalid `packet_type` = {packet_type} (the reserved bits 3 to 7 should be zero)"),
+			Self::ChecksumMismatch{expected, got, offset} => write!(f, "Ogg packet checksum not match at offset {offset}: should be 0x{expected:x}, got 0x{got:x}"),
+			Self::TooManySegments(num_segments) => write!(f, "The segment table is too long: {num_segments} > 255"),
+			Self::DataSizeMismatch{expected, got} => write!(f, "The data size doesn't match the segment table: should be {expected}, got {got}"),
+			Self::SequenceGap{stream_id, expected, got} => write!(f, "Ogg packet sequence gap in stream 0x{stream_id:08x}: expected `packet_index` = {expected}, got {got}"),
+			Self::SequenceRegression{stream_id, prev, got} => write!(f, "Ogg packet sequence regression in stream 0x{stream_id:08x}: `packet_index` = {got} after {prev}"),
+			Self::UnterminatedPacket{stream_id, packet_index} => write!(f, "Ogg packet {packet_index} of stream 0x{stream_id:08x}: the segment table doesn't terminate the data"),
+			Self::MissingBos{stream_id, packet_index} => write!(f, "Ogg packet {packet_index} of stream 0x{stream_id:08x}: the stream doesn't begin with a BOS packet"),
+			Self::BosNotFirst{stream_id, packet_index} => write!(f, "Ogg packet {packet_index} of stream 0x{stream_id:08x}: a BOS packet after the other packets of the stream"),
+			Self::GranuleRegression{stream_id, prev, got} => write!(f, "Ogg granule position regression in stream 0x{stream_id:08x}: {got} after {prev}"),
+			Self::Truncated{got, needed} => write!(f, "The Ogg stream ended in the middle of a packet: got {got} of {needed} bytes"),
+			Self::BufferOverflow{limit, needed} => write!(f, "The Ogg packet needs {needed} bytes of buffer, exceeds the limit of {limit} bytes"),
+			Self::TrailingBytes{packet_size, got} => write!(f, "The bytes have {} bytes after the Ogg packet of {packet_size} bytes", got - packet_size),
+			Self::WriteAfterEos{stream_id} => write!(f, "Can't write to the stream 0x{stream_id:08x} after it was marked as EOS"),
+			Self::MalformedSegmentTable{num_segments, data_length} => write!(f, "The segment table of {num_segments} segments claims {data_length} bytes of data, exceeds the max of {} bytes", OggPacket::MAX_PAYLOAD),
+		}
+	}
+}
+
+impl std::error::Error for OggError {}
+
+impl OggError {
+	/// * Move the byte offset of the error by `base`, for the errors of a packet parsed from the middle of the input
+	fn at_offset(self, base: u64) -> Self {
+		match self {
+			Self::ChecksumMismatch{expected, got, offset} => Self::ChecksumMismatch{expected, got, offset: base + offset},
+			e => e,
+		}
+	}
+}
+
+impl From<OggError> for io::Error {
+	fn from(err: OggError) -> Self {
+		match err {
+			OggError::UnexpectedEof{..} | OggError::Truncated{..} => io::Error::new(ErrorKind::UnexpectedEof, err),
+			OggError::WriteAfterEos{..} => io::Error::new(ErrorKind::InvalidInput, err),
+			_ => io::Error::new(ErrorKind::InvalidData, err),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OggPacketType {
 	/// * The middle packets
 	Continuation = 0,
@@ -17,10 +205,125 @@ pub enum OggPacketType {
 
 	/// * The last packet of a stream
 	EndOfStream = 4,
+
+	/// * The only packet of a stream, which is both the first and the last, e.g. a metadata-only stream
+	BeginAndEndOfStream = 6,
+}
+
+/// * The header type byte of a packet as bit flags, for setting any combination of them, e.g. `PageFlags::CONTINUED | PageFlags::END_OF_STREAM`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub struct PageFlags(pub u8);
+
+impl PageFlags {
+	/// * The first segment continues a logical packet from the previous packet
+	pub const CONTINUED: Self = Self(0x01);
+
+	/// * The first packet of a stream
+	pub const BEGIN_OF_STREAM: Self = Self(0x02);
+
+	/// * The last packet of a stream
+	pub const END_OF_STREAM: Self = Self(0x04);
+
+	/// * The bits 3 to 7 which are reserved by the specification and should be zero
+	pub const RESERVED: Self = Self(0xF8);
+
+	/// * Get the raw header type byte
+	pub fn bits(&self) -> u8 {
+		self.0
+	}
+
+	/// * If all of the flags of `other` are set
+	pub fn contains(&self, other: Self) -> bool {
+		self.0 & other.0 == other.0
+	}
+}
+
+impl std::ops::BitOr for PageFlags {
+	type Output = Self;
+	fn bitor(self, other: Self) -> Self {
+		Self(self.0 | other.0)
+	}
+}
+
+impl std::ops::BitOrAssign for PageFlags {
+	fn bitor_assign(&mut self, other: Self) {
+		self.0 |= other.0;
+	}
+}
+
+/// * The fields of the header of a packet, decoded by `OggPacket::parse_header()` without the data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageHeader {
+	pub version: u8,
+
+	/// * The header type, including the reserved bits
+	pub flags: PageFlags,
+
+	pub granule_position: u64,
+
+	pub stream_id: u32,
+
+	pub packet_index: u32,
+
+	/// * The checksum stored in the header, it's not verified
+	pub checksum: u32,
+
+	/// * The number of segments in the segment table
+	pub num_segments: u8,
+
+	/// * The size of the whole packet: the header, the segment table and the data
+	pub page_length: usize,
+}
+
+impl PageHeader {
+	/// * The size of the data of the packet
+	pub fn data_length(&self) -> usize {
+		self.page_length - OggPacket::HEADER_SIZE - self.num_segments as usize
+	}
+}
+
+/// * The streaming calculation of the checksum of the packets, over the regions of the bytes in any number of `update()` calls.
+/// * The checksum of a packet is calculated with its checksum field as zeros, e.g. update with the bytes `[..22]`, then `[0u8; 4]`, then `[26..]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OggCrc {
+	crc: u32,
+}
+
+impl OggCrc {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// * Process the next bytes
+	pub fn update(&mut self, data: &[u8]) {
+		self.crc = OggPacket::crc(self.crc, data);
+	}
+
+	/// * Get the checksum of all of the processed bytes
+	pub fn finalize(self) -> u32 {
+		self.crc
+	}
+}
+
+/// * The limits for parsing the packets from untrusted input, see `OggPacket::from_bytes_with_limits()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+	/// * The max size of a serialized packet, including its header
+	pub max_page_size: usize,
+}
+
+impl Default for ParseLimits {
+	fn default() -> Self {
+		Self {
+			max_page_size: OggPacket::MAX_PAGE_SIZE,
+		}
+	}
 }
 
 /// * An ogg packet as a stream container
+/// * The equality and the hash are content-based: the stored `checksum` is ignored since it's derived from the other fields.
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OggPacket {
 	/// Ogg Version must be zero
 	pub version: u8,
@@ -28,8 +331,13 @@ pub struct OggPacket {
 	/// * The first packet should be `OggPacketType::BeginOfStream`
 	/// * The last packet should be `OggPacketType::EndOfStream`
 	/// * The others should be `OggPacketType::Continuation`
+	/// * A stream of only one packet has `OggPacketType::BeginAndEndOfStream`
 	pub packet_type: OggPacketType,
 
+	/// * If the first segment of this packet continues a logical packet from the previous packet, this is the flag 0x01 of the header type.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub is_continued: bool,
+
 	/// * For vorbis, this field indicates when you had decoded from the first packet to this packet,
 	///   and you had finished decoding this packet, how many of the audio frames you should get.
 	pub granule_position: u64,
@@ -44,9 +352,11 @@ pub struct OggPacket {
 	pub checksum: u32,
 
 	/// * A table indicates each segment's size, the max is 255. And the size of the table also couldn't exceed 255.
+	#[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
 	pub segment_table: Vec<u8>,
 
 	/// * The data encapsulated in the Ogg Stream
+	#[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
 	pub data: Vec<u8>,
 }
 
@@ -56,6 +366,7 @@ impl OggPacket {
 		Self {
 			version: 0,
 			packet_type,
+			is_continued: false,
 			granule_position: 0,
 			stream_id,
 			packet_index,
@@ -65,34 +376,189 @@ impl OggPacket {
 		}
 	}
 
+	/// Create a new Ogg packet with any combination of the flags of the header type
+	/// * The flags other than `PageFlags::CONTINUED`, `PageFlags::BEGIN_OF_STREAM` and `PageFlags::END_OF_STREAM` are ignored.
+	pub fn new_with_flags(stream_id: u32, flags: PageFlags, packet_index: u32) -> Self {
+		let mut ret = Self::new(stream_id, OggPacketType::Continuation, packet_index);
+		ret.set_flags(flags);
+		ret
+	}
+
+	/// Relabel the packet with another stream ID, e.g. for remuxing. The checksum is recalculated by `into_bytes()`.
+	pub fn with_stream_id(mut self, stream_id: u32) -> Self {
+		self.stream_id = stream_id;
+		self
+	}
+
+	/// Renumber the packet with another packet index, e.g. for remuxing. The checksum is recalculated by `into_bytes()`.
+	pub fn with_sequence(mut self, packet_index: u32) -> Self {
+		self.packet_index = packet_index;
+		self
+	}
+
+	/// Create a new Ogg packet with `capacity` bytes of data reserved, and the segment table reserved for 255 segments, to avoid the reallocations while writing
+	pub fn with_capacity(stream_id: u32, packet_type: OggPacketType, packet_index: u32, capacity: usize) -> Self {
+		let mut ret = Self::new(stream_id, packet_type, packet_index);
+		ret.segment_table.reserve(255);
+		ret.data.reserve(capacity.min(Self::MAX_PAYLOAD));
+		ret
+	}
+
+	/// The max data size of a packet: 255 segments of 255 bytes
+	pub const MAX_PAYLOAD: usize = 255 * 255;
+
+	/// The size of the fixed fields of the header before the segment table
+	pub const HEADER_SIZE: usize = 27;
+
+	/// The max size of a serialized packet: the header with 255 segments, plus 255 segments of 255 bytes
+	pub const MAX_PAGE_SIZE: usize = Self::HEADER_SIZE + 255 + Self::MAX_PAYLOAD;
+
+	/// The capture pattern at the beginning of each packet. The parser, the reader and the writer accept a custom one for the containers built on the Ogg framing.
+	pub const CAPTURE_PATTERN: [u8; 4] = *b"OggS";
+
+	/// The size of the header of the serialized packet: the fixed fields plus the segment table
+	pub fn page_overhead(&self) -> usize {
+		Self::HEADER_SIZE + self.segment_table.len()
+	}
+
+	/// The size of the serialized packet: the header plus the data, without serializing it
+	pub fn total_page_size(&self) -> usize {
+		self.page_overhead() + self.data.len()
+	}
+
 	/// Write some data to the packet, returns the actual written bytes.
 	pub fn write(&mut self, data: &[u8]) -> usize {
-		let mut written = 0usize;
-		let mut to_write = data.len();
-		if to_write == 0 {
-			return 0;
+		self.write_limited(data, Self::MAX_PAYLOAD)
+	}
+
+	/// Write as much of the data as the packet could hold, returns the rest of the data which didn't fit, or `None` if all of the data was written.
+	/// * When the rest is returned, the packet is full, seal it and write the rest to a new packet.
+	pub fn write_all<'a>(&mut self, data: &'a [u8]) -> Option<&'a [u8]> {
+		let written = self.write(data);
+		if written == data.len() {
+			None
+		} else {
+			Some(&data[written..])
 		}
+	}
+
+	/// Write an empty logical packet, which is a single zero-sized segment, returns false if the segment table is full.
+	/// * `write()` writes nothing for empty data, use this for the codecs which use empty packets as markers.
+	/// * If the last segment is 255 bytes, the zero-sized segment terminates that logical packet instead.
+	pub fn write_empty_packet(&mut self) -> bool {
+		if self.segment_table.len() >= 255 {
+			return false;
+		}
+		self.segment_table.push(0);
+		true
+	}
+
+	/// Write some data to the packet without letting the data size exceed `max_payload`, returns the actual written bytes.
+	/// * Only whole segments are written. A segment shorter than 255 bytes ends the data, so it's written only if the rest of the data fits in it.
+	pub fn write_limited(&mut self, data: &[u8], max_payload: usize) -> usize {
+		let mut written = 0usize;
 		while self.segment_table.len() < 255 {
-			if to_write >= 255 {
-				let new_pos = written + 255;
-				self.segment_table.push(255);
-				self.data.extend(data[written..new_pos].to_vec());
-				written = new_pos;
-				to_write -= 255;
-			} else {
-				if to_write == 0 {
-					break;
-				}
-				let new_pos = written + to_write;
-				self.segment_table.push(to_write as u8);
-				self.data.extend(data[written..new_pos].to_vec());
-				written = new_pos;
+			let segment_size = (data.len() - written).min(255);
+			if segment_size == 0 || self.data.len() + segment_size > max_payload {
+				break;
+			}
+			let new_pos = written + segment_size;
+			self.segment_table.push(segment_size as u8);
+			self.data.extend(&data[written..new_pos]);
+			written = new_pos;
+			if segment_size < 255 {
 				break;
 			}
 		}
 		written
 	}
 
+	/// Write a part of a logical packet to the packet, returns the actual written bytes.
+	/// * `is_continued` tells if the data continues a logical packet from the previous packet, it sets the continued flag only if this packet is still empty.
+	/// * If all of the data is written, the logical packet is terminated by a segment shorter than 255 bytes, or a zero-sized segment if its size is a multiple of 255.
+	/// * Otherwise the packet is full and its last segment is 255 bytes, write the rest of the data to the next packet with `is_continued` set to true.
+	pub fn append_page_data(&mut self, data: &[u8], is_continued: bool) -> usize {
+		self.append_limited(data, is_continued, Self::MAX_PAYLOAD).0
+	}
+
+	/// Write a part of a logical packet without letting the data size exceed `max_payload`, returns the actual written bytes and if the logical packet is finished.
+	fn append_limited(&mut self, data: &[u8], is_continued: bool, max_payload: usize) -> (usize, bool) {
+		if self.segment_table.is_empty() {
+			self.is_continued = is_continued;
+		}
+		let free_segments = 255usize.saturating_sub(self.segment_table.len());
+		let free_bytes = max_payload.saturating_sub(self.data.len());
+		let full_segments = data.len() / 255;
+		let fits = full_segments < free_segments && data.len() <= free_bytes;
+		let segments_to_write = if fits {
+			full_segments
+		} else {
+			let segments = free_segments.min(free_bytes / 255);
+			if segments * 255 == data.len() {
+				// No slot left for the terminating segment, leave a full segment for the next packet to terminate with.
+				segments.saturating_sub(1)
+			} else {
+				segments
+			}
+		};
+		for _ in 0..segments_to_write {
+			self.segment_table.push(255);
+		}
+		let mut written = segments_to_write * 255;
+		if fits {
+			let last_size = data.len() - written;
+			self.segment_table.push(last_size as u8);
+			written = data.len();
+		}
+		self.data.extend(&data[..written]);
+		(written, fits)
+	}
+
+	/// Get the flags of the header type, which combines the `packet_type` and the `is_continued` flag
+	pub fn flags(&self) -> PageFlags {
+		PageFlags(self.packet_type as u8 | self.is_continued as u8)
+	}
+
+	/// Set the `packet_type` and the `is_continued` flag from the flags of the header type
+	pub fn set_flags(&mut self, flags: PageFlags) {
+		self.packet_type = match flags.bits() & 6 {
+			0 => OggPacketType::Continuation,
+			2 => OggPacketType::BeginOfStream,
+			4 => OggPacketType::EndOfStream,
+			_ => OggPacketType::BeginAndEndOfStream,
+		};
+		self.is_continued = flags.contains(PageFlags::CONTINUED);
+	}
+
+	/// Get a snapshot of the header fields and the sizes without the data, it's cheap and `Copy` for logging.
+	pub fn as_page_info(&self) -> PageInfo {
+		PageInfo {
+			stream_id: self.stream_id,
+			sequence: self.packet_index,
+			flags: self.flags(),
+			granule: self.granule_position,
+			payload_len: self.data.len(),
+			segment_count: self.segment_table.len(),
+			byte_len: self.total_page_size(),
+			is_eos: self.is_last_page(),
+		}
+	}
+
+	/// If the packet is the first packet of a stream, which has the BOS flag
+	pub fn is_first_page(&self) -> bool {
+		self.flags().contains(PageFlags::BEGIN_OF_STREAM)
+	}
+
+	/// If the packet is the last packet of a stream, which has the EOS flag
+	pub fn is_last_page(&self) -> bool {
+		self.flags().contains(PageFlags::END_OF_STREAM)
+	}
+
+	/// If the data of the packet begins with a new logical packet, which means the continued flag is clear
+	pub fn is_fresh_packet(&self) -> bool {
+		!self.is_continued
+	}
+
 	/// Clear all data inside the packet
 	pub fn clear(&mut self) {
 		self.segment_table = Vec::new();
@@ -121,12 +587,24 @@ impl OggPacket {
 		self.get_segments().into_iter().flatten().collect()
 	}
 
+	/// The data of the segments until the first segment shorter than 255, which is the first packet in a BOS packet
+	fn get_first_segment_run(&self) -> Vec<u8> {
+		let mut size = 0usize;
+		for &s in self.segment_table.iter() {
+			size += s as usize;
+			if s < 255 {
+				break;
+			}
+		}
+		self.data[..size].to_vec()
+	}
+
 	/// Read all of the data as a flattened `Vec<u8>` and consume self
 	pub fn into_inner(self) -> Vec<u8> {
 		self.get_inner_data()
 	}
 
-	/// Calculate the checksum
+	/// Calculate the checksum, `crc` is the checksum of the bytes before `data`, or 0 to begin with. See `OggCrc` for the streaming calculation.
 	pub fn crc(mut crc: u32, data: &[u8]) -> u32 {
         type CrcTableType = [u32; 256];
         fn ogg_generate_crc_table() -> CrcTableType {
@@ -159,9 +637,12 @@ impl OggPacket {
 		if ogg_packet.len() < 27 {
 			Err(io::Error::new(ErrorKind::InvalidData, format!("The given packet is too small: {} < 27", ogg_packet.len())))
 		} else {
-			let mut field_cleared = ogg_packet.to_vec();
-			field_cleared[22..26].copy_from_slice(&[0u8; 4]);
-			Ok(Self::crc(0, &field_cleared))
+			// The checksum field is calculated as zeros, the bytes are processed directly without copying.
+			let mut crc = OggCrc::new();
+			crc.update(&ogg_packet[..22]);
+			crc.update(&[0u8; 4]);
+			crc.update(&ogg_packet[26..]);
+			Ok(crc.finalize())
 		}
 	}
 
@@ -172,74 +653,315 @@ impl OggPacket {
 		Ok(())
 	}
 
+	/// * Walk through every packet in the buffer and rewrite its checksum field with the recalculated checksum, returns how many packets were corrected.
+	/// * It stops at the truncated packet at the end of the buffer. A packet without the capture pattern is an error.
+	/// * Only the packets beginning with `OggS` are supported, the packets with a custom capture pattern are `OggError::BadCapturePattern`.
+	pub fn repair_checksums(buffer: &mut [u8]) -> io::Result<usize> {
+		let mut offset = 0usize;
+		let mut corrected = 0usize;
+		while offset < buffer.len() {
+			let packet_length = match Self::from_bytes_ref(&buffer[offset..]) {
+				Ok((header, payload)) => header.len() + payload.len(),
+				Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+				Err(e) => return Err(e),
+			};
+			let ogg_packet = &mut buffer[offset..offset + packet_length];
+			let checksum = Self::get_checksum(ogg_packet)?;
+			if ogg_packet[22..26] != checksum.to_le_bytes() {
+				Self::fill_checksum_field(ogg_packet)?;
+				corrected += 1;
+			}
+			offset += packet_length;
+		}
+		Ok(corrected)
+	}
+
+	/// Calculate the checksum of the serialized packet in a single pass over the fields, without serializing it.
+	/// * The checksum field is processed as zeros inline, the `checksum` isn't used.
+	pub fn compute_checksum(&self) -> u32 {
+		self.compute_checksum_with(Self::CAPTURE_PATTERN)
+	}
+
+	/// Calculate the checksum of the packet serialized with a custom capture pattern
+	fn compute_checksum_with(&self, capture_pattern: [u8; 4]) -> u32 {
+		let mut crc = OggCrc::new();
+		crc.update(&capture_pattern);
+		crc.update(&[self.version, self.flags().bits()]);
+		crc.update(&self.granule_position.to_le_bytes());
+		crc.update(&self.stream_id.to_le_bytes());
+		crc.update(&self.packet_index.to_le_bytes());
+		crc.update(&[0u8; 4]);
+		crc.update(&[self.segment_table.len() as u8]);
+		crc.update(&self.segment_table);
+		crc.update(&self.data);
+		crc.finalize()
+	}
+
+	/// Update the `checksum` field to the checksum of the serialized packet
+	pub fn recompute_checksum(&mut self) {
+		self.checksum = self.compute_checksum();
+	}
+
+	/// Set the granule position and take out the packet for serializing, then replace it with the next packet of the stream for writing.
+	/// * If the packet ends with a segment of 255, the logical packet goes on in the next packet, so the next packet has the continued flag.
+	fn seal(&mut self, granule_position: u64, is_end_of_stream: bool) -> Self {
+		self.granule_position = granule_position;
+		if is_end_of_stream {
+			// The only packet of a stream keeps its BOS flag.
+			self.set_flags(self.flags() | PageFlags::END_OF_STREAM);
+			mem::take(self)
+		} else {
+			let mut next = OggPacket::new(self.stream_id, OggPacketType::Continuation, self.packet_index + 1);
+			// An empty packet passes the continuation on.
+			next.is_continued = match self.segment_table.last() {
+				Some(&size) => size == 255,
+				None => self.is_continued,
+			};
+			mem::replace(self, next)
+		}
+	}
+
+	/// Check if the packet is valid for serialization, returns the first structural error, for the packets built by hand:
+	/// * The version is zero: `OggError::BadVersion`
+	/// * No more than 255 segments: `OggError::TooManySegments`
+	/// * The sum of the segment table equals the data size: `OggError::DataSizeMismatch`
+	/// * The flags are always within the defined bits, since they are stored as `packet_type` and `is_continued`.
+	pub fn verify(&self) -> Result<(), OggError> {
+		if self.version != 0 {
+			return Err(OggError::BadVersion(self.version));
+		}
+		if self.segment_table.len() > 255 {
+			return Err(OggError::TooManySegments(self.segment_table.len()));
+		}
+		let expected = self.get_inner_data_size();
+		if self.data.len() != expected {
+			return Err(OggError::DataSizeMismatch{expected, got: self.data.len()});
+		}
+		Ok(())
+	}
+
+	/// The same as `verify()`
+	pub fn validate(&self) -> Result<(), OggError> {
+		self.verify()
+	}
+
+	/// Verify the packet and serialize it to bytes, so that a corrupt packet won't be produced.
+	pub fn try_into_bytes(self) -> Result<Vec<u8>, OggError> {
+		self.verify()?;
+		Ok(self.into_bytes())
+	}
+
 	/// Serialize the packet to bytes. Only in the bytes form can calculate the checksum.
+	/// * The packet isn't validated, if you had modified the `segment_table` or the `data` manually, use `try_into_bytes()` instead.
 	pub fn into_bytes(self) -> Vec<u8> {
-		let mut ret: Vec<u8> = [
-			b"OggS" as &[u8],
-			&[self.version],
-			&[self.packet_type as u8],
-			&self.granule_position.to_le_bytes() as &[u8],
-			&self.stream_id.to_le_bytes() as &[u8],
-			&self.packet_index.to_le_bytes() as &[u8],
-			&0u32.to_le_bytes() as &[u8],
-			&[self.segment_table.len() as u8],
-			&self.segment_table,
-			&self.data,
-		].into_iter().flatten().copied().collect();
-		Self::fill_checksum_field(&mut ret).unwrap();
+		self.to_bytes()
+	}
+
+	/// Serialize the packet to bytes without consuming it, the same as `into_bytes()`.
+	/// * The bytes are allocated once for the whole packet, the checksum is calculated before serializing by `compute_checksum()`.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		self.to_bytes_with_capture_pattern(Self::CAPTURE_PATTERN)
+	}
+
+	/// Serialize the packet with a custom capture pattern instead of `OggS`, for the containers built on the Ogg framing. The checksum covers the custom capture pattern.
+	pub fn to_bytes_with_capture_pattern(&self, capture_pattern: [u8; 4]) -> Vec<u8> {
+		let mut ret = Vec::with_capacity(self.total_page_size());
+		self.extend_header(self.compute_checksum_with(capture_pattern), capture_pattern, &mut ret);
+		ret.extend(&self.data);
 		ret
 	}
 
+	/// Serialize the packet directly to the writer without consuming it, the same bytes as `into_bytes()`.
+	/// * Only the header is allocated, the checksum is calculated over the header and the data in place.
+	pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		self.write_to_with(writer, Self::CAPTURE_PATTERN)
+	}
+
+	/// Serialize the packet directly to the writer with a custom capture pattern
+	fn write_to_with<W: Write>(&self, writer: &mut W, capture_pattern: [u8; 4]) -> io::Result<()> {
+		self.write_to_with_checksum(writer, self.compute_checksum_with(capture_pattern), capture_pattern)
+	}
+
+	/// Serialize the packet directly to the writer with the checksum already calculated for the capture pattern
+	fn write_to_with_checksum<W: Write>(&self, writer: &mut W, checksum: u32, capture_pattern: [u8; 4]) -> io::Result<()> {
+		let mut header = Vec::with_capacity(self.page_overhead());
+		self.extend_header(checksum, capture_pattern, &mut header);
+		writer.write_all(&header)?;
+		writer.write_all(&self.data)
+	}
+
+	/// Append the header of the packet with the given checksum field and capture pattern to `out`.
+	fn extend_header(&self, checksum: u32, capture_pattern: [u8; 4], out: &mut Vec<u8>) {
+		out.extend(capture_pattern);
+		out.extend([self.version, self.flags().bits()]);
+		out.extend(self.granule_position.to_le_bytes());
+		out.extend(self.stream_id.to_le_bytes());
+		out.extend(self.packet_index.to_le_bytes());
+		out.extend(checksum.to_le_bytes());
+		out.push(self.segment_table.len() as u8);
+		out.extend(&self.segment_table);
+	}
+
+	/// Serialize the header of the packet: the 27 bytes of the fixed fields and the segment table.
+	/// * The checksum field is the `checksum` as is, so that you can compare it with the original bytes.
+	pub fn header_bytes(&self) -> Vec<u8> {
+		let mut header = Vec::with_capacity(self.page_overhead());
+		self.extend_header(self.checksum, Self::CAPTURE_PATTERN, &mut header);
+		header
+	}
+
 	/// Retrieve the packet length in bytes
+	/// * The length is at most `MAX_PAGE_SIZE`, because the segment table has at most 255 segments of 255 bytes.
+	///   So an `UnexpectedEof` can always be satisfied by more bytes, a corrupt segment table is found by the checksum after them.
+	/// * Only the packets beginning with `OggS` are supported, a custom capture pattern is `OggError::BadCapturePattern`. So are `parse_header()`, `from_bytes_ref()` and `scan_pages()` which are built on it.
 	pub fn get_length(ogg_packet: &[u8]) -> io::Result<usize> {
+		Ok(Self::length_with(ogg_packet, false)?)
+	}
+
+	/// * Retrieve the packet length in bytes even if its version isn't zero, like `from_bytes_any_version()`. The layout of the header is assumed to be the same as the version 0.
+	pub fn get_length_any_version(ogg_packet: &[u8]) -> io::Result<usize> {
+		Ok(Self::length_with(ogg_packet, true)?)
+	}
+
+	/// Sum the segment table into the data size, a sum over `MAX_PAYLOAD` is `OggError::MalformedSegmentTable` instead of waiting for the bytes of it.
+	fn data_length_of(segment_table: &[u8]) -> Result<usize, OggError> {
+		let data_length: usize = segment_table.iter().map(|&s|s as usize).sum();
+		if data_length > Self::MAX_PAYLOAD {
+			return Err(OggError::MalformedSegmentTable{num_segments: segment_table.len(), data_length});
+		}
+		Ok(data_length)
+	}
+
+	/// Retrieve the packet length, a nonzero version is an `OggError::BadVersion` unless `any_version` is set
+	fn length_with(ogg_packet: &[u8], any_version: bool) -> Result<usize, OggError> {
 		if ogg_packet.len() < 27 {
-			Err(io::Error::new(ErrorKind::UnexpectedEof, format!("The given ogg page size is too small: {} < 27", ogg_packet.len())))
+			Err(OggError::UnexpectedEof{got: ogg_packet.len(), needed: 27})
 		} else if ogg_packet[0..4] != *b"OggS" {
-			Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing Ogg packet: expected `OggS`, got `{}`", String::from_utf8_lossy(&ogg_packet[0..4]))))
-		} else if ogg_packet[4] != 0 {
-			Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing Ogg packet: invalid `version` = {} (should be zero)", ogg_packet[4])))
+			Err(OggError::BadCapturePattern(ogg_packet[0..4].try_into().unwrap()))
+		} else if ogg_packet[4] != 0 && !any_version {
+			Err(OggError::BadVersion(ogg_packet[4]))
 		} else {
-			match ogg_packet[5] {
-				0 | 2 | 4 => (),
-				o => return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing Ogg packet: invalid `packet_type` = {o} (should be 0, 2, 4)"))),
-			}
 			let num_segments = ogg_packet[26] as usize;
-			let data_start = 27 + num_segments;
-			let segment_table = &ogg_packet[27..data_start];
-			let data_length: usize = segment_table.iter().map(|&s|s as usize).sum();
+			let data_start = Self::HEADER_SIZE + num_segments;
+			if data_start > ogg_packet.len() {
+				return Err(OggError::UnexpectedEof{got: ogg_packet.len(), needed: data_start});
+			}
+			let segment_table = &ogg_packet[Self::HEADER_SIZE..data_start];
+			let data_length = Self::data_length_of(segment_table)?;
 			Ok(data_start + data_length)
 		}
 	}
 
+	/// * Split the packet at the beginning of the bytes into the header (including the segment table) and the payload without copying.
+	/// * The checksum isn't verified, so a corrupt packet can be compared with its re-serialized bytes. The packet occupies `header.len() + payload.len()` bytes.
+	/// * The version isn't checked either, it's the byte at offset 4 of the header.
+	pub fn from_bytes_ref(ogg_packet: &[u8]) -> io::Result<(&[u8], &[u8])> {
+		let packet_length = Self::get_length_any_version(ogg_packet)?;
+		if ogg_packet.len() < packet_length {
+			return Err(OggError::UnexpectedEof{got: ogg_packet.len(), needed: packet_length}.into());
+		}
+		let data_start = 27 + ogg_packet[26] as usize;
+		Ok((&ogg_packet[..data_start], &ogg_packet[data_start..packet_length]))
+	}
+
 	/// Deserialize the packet
 	pub fn from_bytes(ogg_packet: &[u8], packet_length: &mut usize) -> io::Result<Self> {
+		Ok(Self::parse(ogg_packet, packet_length)?)
+	}
+
+	/// * Decode the header of the packet without copying the data, only the fixed fields and the segment table are needed, the data doesn't need to be in the bytes.
+	/// * Much cheaper than `from_bytes()` for indexing, but the checksum isn't verified. A nonzero version is accepted and kept in the `version` field.
+	pub fn parse_header(ogg_packet: &[u8]) -> io::Result<PageHeader> {
+		let page_length = Self::get_length_any_version(ogg_packet)?;
+		let read_u32 = |offset: usize|u32::from_le_bytes(ogg_packet[offset..offset + 4].try_into().unwrap());
+		Ok(PageHeader {
+			version: ogg_packet[4],
+			flags: PageFlags(ogg_packet[5]),
+			granule_position: u64::from_le_bytes(ogg_packet[6..14].try_into().unwrap()),
+			stream_id: read_u32(14),
+			packet_index: read_u32(18),
+			checksum: read_u32(22),
+			num_segments: ogg_packet[26],
+			page_length,
+		})
+	}
+
+	/// * Deserialize the packet from untrusted input, a packet larger than `limits.max_page_size` is rejected by its header before anything is allocated for its data.
+	/// * The rejected packet is reported as `OggError::BufferOverflow`.
+	pub fn from_bytes_with_limits(ogg_packet: &[u8], packet_length: &mut usize, limits: ParseLimits) -> io::Result<Self> {
+		let length = Self::get_length(ogg_packet)?;
+		if length > limits.max_page_size {
+			*packet_length = length;
+			return Err(OggError::BufferOverflow{limit: limits.max_page_size, needed: length}.into());
+		}
+		Self::from_bytes(ogg_packet, packet_length)
+	}
+
+	/// * Deserialize the packet even if its checksum doesn't match, returns the packet and if the checksum is valid.
+	/// * Repair tools need the contents of the packet with a wrong checksum, the `checksum` field is the one stored in the bytes.
+	pub fn from_bytes_lenient(ogg_packet: &[u8], packet_length: &mut usize) -> io::Result<(Self, bool)> {
+		let (packet, checksum) = Self::parse_unchecked(ogg_packet, packet_length, false, Self::CAPTURE_PATTERN)?;
+		let is_valid = packet.checksum == checksum;
+		Ok((packet, is_valid))
+	}
+
+	/// * Deserialize the packet even if its version isn't zero, for the files of a future version or oddball muxers. The version is kept in the `version` field.
+	/// * The layout of the header is assumed to be the same as the version 0, the checksum is verified as usual.
+	pub fn from_bytes_any_version(ogg_packet: &[u8], packet_length: &mut usize) -> io::Result<Self> {
+		Ok(Self::parse_with(ogg_packet, packet_length, true, Self::CAPTURE_PATTERN)?)
+	}
+
+	/// * Deserialize the packet which begins with a custom capture pattern instead of `OggS`, for the containers built on the Ogg framing.
+	/// * The checksum covers the custom capture pattern, see `to_bytes_with_capture_pattern()`.
+	pub fn from_bytes_with_capture_pattern(ogg_packet: &[u8], packet_length: &mut usize, capture_pattern: [u8; 4]) -> io::Result<Self> {
+		Ok(Self::parse_with(ogg_packet, packet_length, false, capture_pattern)?)
+	}
+
+	fn parse(ogg_packet: &[u8], packet_length: &mut usize) -> Result<Self, OggError> {
+		Self::parse_with(ogg_packet, packet_length, false, Self::CAPTURE_PATTERN)
+	}
+
+	/// Parse the packet beginning with `capture_pattern`, a nonzero version is an `OggError::BadVersion` unless `any_version` is set
+	fn parse_with(ogg_packet: &[u8], packet_length: &mut usize, any_version: bool, capture_pattern: [u8; 4]) -> Result<Self, OggError> {
+		let (packet, checksum) = Self::parse_unchecked(ogg_packet, packet_length, any_version, capture_pattern)?;
+		if packet.checksum != checksum {
+			Err(OggError::ChecksumMismatch{expected: checksum, got: packet.checksum, offset: 0})
+		} else {
+			Ok(packet)
+		}
+	}
+
+	/// Parse the packet along with the calculated checksum, without comparing it with the `checksum` field
+	fn parse_unchecked(ogg_packet: &[u8], packet_length: &mut usize, any_version: bool, capture_pattern: [u8; 4]) -> Result<(Self, u32), OggError> {
 		if ogg_packet.len() < 27 {
-			Err(io::Error::new(ErrorKind::UnexpectedEof, format!("The given data size is too small: {} < 27", ogg_packet.len())))
-		} else if ogg_packet[0..4] != *b"OggS" {
-			Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing Ogg packet: expected `OggS`, got `{}`", String::from_utf8_lossy(&ogg_packet[0..4]))))
-		} else if ogg_packet[4] != 0 {
-			Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing Ogg packet: invalid `version` = {} (should be zero)", ogg_packet[4])))
+			Err(OggError::UnexpectedEof{got: ogg_packet.len(), needed: 27})
+		} else if ogg_packet[0..4] != capture_pattern {
+			Err(OggError::BadCapturePattern(ogg_packet[0..4].try_into().unwrap()))
+		} else if ogg_packet[4] != 0 && !any_version {
+			Err(OggError::BadVersion(ogg_packet[4]))
 		} else {
-			let packet_type = match ogg_packet[5] {
+			// The reserved bits 3 to 7 of the header type are ignored, `OggStreamReader` reports them in strict mode.
+			let packet_type = match ogg_packet[5] & 6 {
 				0 => OggPacketType::Continuation,
 				2 => OggPacketType::BeginOfStream,
 				4 => OggPacketType::EndOfStream,
-				o => return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing Ogg packet: invalid `packet_type` = {o} (should be 0, 2, 4)"))),
+				_ => OggPacketType::BeginAndEndOfStream,
 			};
 			let num_segments = ogg_packet[26] as usize;
 			let data_start = 27 + num_segments;
 			if data_start > ogg_packet.len() {
-				return Err(io::Error::new(ErrorKind::UnexpectedEof, format!("The given data size is too small: {}", ogg_packet.len())));
+				return Err(OggError::UnexpectedEof{got: ogg_packet.len(), needed: data_start});
 			}
 			let segment_table = &ogg_packet[27..data_start];
-			let data_length: usize = segment_table.iter().map(|&s|s as usize).sum();
+			let data_length = Self::data_length_of(segment_table)?;
 			*packet_length = data_start + data_length;
 			if ogg_packet.len() < *packet_length {
-				Err(io::Error::new(ErrorKind::UnexpectedEof, format!("The given data size is too small: {} < {packet_length}", ogg_packet.len())))
+				Err(OggError::UnexpectedEof{got: ogg_packet.len(), needed: *packet_length})
 			} else {
 				let ret = Self{
-					version: 0,
+					version: ogg_packet[4],
 					packet_type,
+					is_continued: ogg_packet[5] & 1 != 0,
 					granule_position: u64::from_le_bytes(ogg_packet[6..14].try_into().unwrap()),
 					stream_id: u32::from_le_bytes(ogg_packet[14..18].try_into().unwrap()),
 					packet_index: u32::from_le_bytes(ogg_packet[18..22].try_into().unwrap()),
@@ -247,31 +969,90 @@ impl OggPacket {
 					segment_table: segment_table.to_vec(),
 					data: ogg_packet[data_start..*packet_length].to_vec(),
 				};
-				let checksum = Self::get_checksum(&ogg_packet[..*packet_length])?;
-				if ret.checksum != checksum {
-					Err(io::Error::new(ErrorKind::InvalidData, format!("Ogg packet checksum not match: should be 0x{:x}, got 0x{:x}", checksum, ret.checksum)))
-				} else {
-					Ok(ret)
-				}
+				let checksum = Self::get_checksum(&ogg_packet[..*packet_length]).unwrap();
+				Ok((ret, checksum))
 			}
 		}
 	}
 
-	/// Deserialize to multiple packets
-	pub fn from_cursor(cursor: &mut Cursor<Vec<u8>>) -> Vec<OggPacket> {
-		let mut data: &[u8] = cursor.get_ref();
+	/// * Deserialize to multiple packets, starting from the current position of the cursor.
+	/// * The cursor is advanced past the successfully parsed packets.
+	/// * If the parsing stopped before the end of the data, the reason is returned along with the packets.
+	pub fn from_cursor(cursor: &mut Cursor<Vec<u8>>) -> (Vec<OggPacket>, Option<OggError>) {
+		let start = (cursor.position() as usize).min(cursor.get_ref().len());
+		let (ret, bytes_read, error) = Self::parse_many(&cursor.get_ref()[start..]);
+		cursor.set_position((start + bytes_read) as u64);
+		(ret, error.map(|e|e.at_offset(start as u64)))
+	}
+
+	/// * Deserialize as many packets as possible from the beginning of the bytes, returns the packets and how many bytes were consumed.
+	/// * The parsing stops at the first packet that is incomplete or invalid, the bytes from it are not consumed.
+	pub fn parse_all(bytes: &[u8]) -> (Vec<OggPacket>, usize) {
+		let (ret, bytes_read, _) = Self::parse_many(bytes);
+		(ret, bytes_read)
+	}
+
+	fn parse_many(bytes: &[u8]) -> (Vec<OggPacket>, usize, Option<OggError>) {
+		let mut data = bytes;
 		let mut packet_length = 0usize;
 		let mut bytes_read = 0usize;
 		let mut ret = Vec::<OggPacket>::new();
-		while let Ok(packet) = Self::from_bytes(data, &mut packet_length) {
-			bytes_read += packet_length;
-			ret.push(packet);
-			data = &data[packet_length..];
-			if data.is_empty() {
-				break;
+		let mut error = None;
+		while !data.is_empty() {
+			match Self::parse(data, &mut packet_length) {
+				Ok(packet) => {
+					bytes_read += packet_length;
+					ret.push(packet);
+					data = &data[packet_length..];
+				}
+				Err(e) => {
+					error = Some(e.at_offset(bytes_read as u64));
+					break;
+				}
+			}
+		}
+		(ret, bytes_read, error)
+	}
+
+	/// * Locate the packets in the bytes without decoding their fields or copying their data, returns the `(offset, length)` of each packet.
+	/// * Only the capture pattern, the version and the lengths are checked. On a bad or incomplete packet, the scan skips to the next `OggS`, a custom capture pattern isn't supported.
+	/// * A packet with a nonzero version is skipped as a bad one, so that the scan doesn't lock on a false capture pattern, locate such packets by `get_length_any_version()`.
+	pub fn scan_pages(bytes: &[u8]) -> Vec<(usize, usize)> {
+		Self::scan(bytes, false)
+	}
+
+	/// * Same as `scan_pages()`, but also verify the checksum of each packet, the packets with a wrong checksum are skipped.
+	pub fn scan_pages_checked(bytes: &[u8]) -> Vec<(usize, usize)> {
+		Self::scan(bytes, true)
+	}
+
+	fn scan(bytes: &[u8], verify_checksum: bool) -> Vec<(usize, usize)> {
+		let mut ret = Vec::<(usize, usize)>::new();
+		let mut offset = 0usize;
+		while offset < bytes.len() {
+			let data = &bytes[offset..];
+			let valid_length = match Self::get_length(data) {
+				Ok(length) if length <= data.len() => {
+					let page = &data[..length];
+					if !verify_checksum || Self::get_checksum(page).is_ok_and(|c|c.to_le_bytes() == page[22..26]) {
+						Some(length)
+					} else {
+						None
+					}
+				}
+				_ => None,
+			};
+			match valid_length {
+				Some(length) => {
+					ret.push((offset, length));
+					offset += length;
+				}
+				None => match data[1..].windows(4).position(|w|w == b"OggS") {
+					Some(next) => offset += 1 + next,
+					None => break,
+				}
 			}
 		}
-		cursor.set_position(bytes_read as u64);
 		ret
 	}
 }
@@ -281,6 +1062,7 @@ impl Debug for OggPacket {
 		f.debug_struct("OggPacket")
 		.field("version", &self.version)
 		.field("packet_type", &self.packet_type)
+		.field("is_continued", &self.is_continued)
 		.field("granule_position", &self.granule_position)
 		.field("stream_id", &self.stream_id)
 		.field("packet_index", &self.packet_index)
@@ -291,11 +1073,103 @@ impl Debug for OggPacket {
 	}
 }
 
-impl Default for OggPacket {
-	fn default() -> Self {
-		Self {
+impl PartialEq for OggPacket {
+	fn eq(&self, other: &Self) -> bool {
+		self.version == other.version
+			&& self.flags() == other.flags()
+			&& self.granule_position == other.granule_position
+			&& self.stream_id == other.stream_id
+			&& self.packet_index == other.packet_index
+			&& self.segment_table == other.segment_table
+			&& self.data == other.data
+	}
+}
+
+impl Eq for OggPacket {}
+
+impl std::hash::Hash for OggPacket {
+	fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+		self.version.hash(state);
+		self.flags().hash(state);
+		self.granule_position.hash(state);
+		self.stream_id.hash(state);
+		self.packet_index.hash(state);
+		self.segment_table.hash(state);
+		self.data.hash(state);
+	}
+}
+
+impl fmt::Display for OggPacket {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		let flags = match self.packet_type {
+			OggPacketType::Continuation => "CONT",
+			OggPacketType::BeginOfStream => "BOS",
+			OggPacketType::EndOfStream => "EOS",
+			OggPacketType::BeginAndEndOfStream => "BOS|EOS",
+		};
+		let continued = if self.is_continued {"|CONTINUED"} else {""};
+		write!(f, "OggPage[stream=0x{:08x} seq={} flags={flags}{continued} granule={} segs={} bytes={}]",
+			self.stream_id,
+			self.packet_index,
+			self.granule_position,
+			self.segment_table.len(),
+			self.data.len(),
+		)
+	}
+}
+
+/// * Writing to a packet writes the data as `OggPacket::write()` does, when the packet is full, an `ErrorKind::WriteZero` error is returned.
+impl Write for OggPacket {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let written = OggPacket::write(self, buf);
+		if written == 0 && !buf.is_empty() {
+			return Err(io::Error::new(ErrorKind::WriteZero, format!("The packet is full: {} bytes in {} segments", self.data.len(), self.segment_table.len())));
+		}
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// * Parse a packet from the beginning of the bytes, the trailing bytes are ignored.
+impl TryFrom<&[u8]> for OggPacket {
+	type Error = OggError;
+
+	fn try_from(ogg_packet: &[u8]) -> Result<Self, Self::Error> {
+		let mut packet_length = 0usize;
+		Self::parse(ogg_packet, &mut packet_length)
+	}
+}
+
+/// * Parse the bytes of exactly one packet, unlike `TryFrom<&[u8]>`, the bytes after the packet are an error.
+impl TryFrom<Vec<u8>> for OggPacket {
+	type Error = OggError;
+
+	fn try_from(ogg_packet: Vec<u8>) -> Result<Self, Self::Error> {
+		let mut packet_length = 0usize;
+		let packet = Self::parse(&ogg_packet, &mut packet_length)?;
+		if packet_length != ogg_packet.len() {
+			return Err(OggError::TrailingBytes{packet_size: packet_length, got: ogg_packet.len()});
+		}
+		Ok(packet)
+	}
+}
+
+/// * Serialize the packet, the same as `into_bytes()`
+impl From<OggPacket> for Vec<u8> {
+	fn from(packet: OggPacket) -> Self {
+		packet.into_bytes()
+	}
+}
+
+impl Default for OggPacket {
+	fn default() -> Self {
+		Self {
 			version: 0,
 			packet_type: OggPacketType::BeginOfStream,
+			is_continued: false,
 			granule_position: 0,
 			stream_id: 0,
 			packet_index: 0,
@@ -306,6 +1180,78 @@ impl Default for OggPacket {
 	}
 }
 
+/// * Serialize the packets and concatenate them into bytes, the inverse of `OggPacket::from_cursor()`. The checksums are recalculated.
+pub fn mux_pages(pages: impl IntoIterator<Item = OggPacket>) -> Vec<u8> {
+	pages.into_iter().flat_map(|page|page.into_bytes()).collect()
+}
+
+/// * Like `mux_pages()`, but each packet is validated, and the `packet_index` of each stream must increase, or `OggError::SequenceRegression` is returned.
+/// * A BOS packet restarts the sequence of its stream.
+pub fn try_mux_pages(pages: impl IntoIterator<Item = OggPacket>) -> Result<Vec<u8>, OggError> {
+	let mut last_sequences = HashMap::<u32, u32>::new();
+	let mut ret = Vec::<u8>::new();
+	for page in pages {
+		let last = last_sequences.insert(page.stream_id, page.packet_index);
+		if !page.is_first_page()
+			&& let Some(prev) = last
+			&& page.packet_index <= prev {
+			return Err(OggError::SequenceRegression{stream_id: page.stream_id, prev, got: page.packet_index});
+		}
+		ret.extend(page.try_into_bytes()?);
+	}
+	Ok(ret)
+}
+
+/// * Splits logical packets into Ogg packets with the correct flags and lacing values.
+/// * The packets are numbered from `packet_index`, the packet numbered 0 is the BOS packet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PacketBuilder {
+	/// * The stream ID of the built packets
+	pub stream_id: u32,
+
+	/// * The index of the next built packet
+	pub packet_index: u32,
+}
+
+impl PacketBuilder {
+	pub fn new(stream_id: u32, packet_index: u32) -> Self {
+		Self {
+			stream_id,
+			packet_index,
+		}
+	}
+
+	/// * Split a whole logical packet into Ogg packets, each of them has its checksum filled.
+	/// * The packet that finishes the logical packet has `granule_position`, the others that only carry a part of it have the granule position -1.
+	/// * If `is_end_of_stream` is true, the last packet is the EOS packet.
+	pub fn build(&mut self, logical_packet: &[u8], granule_position: u64, is_end_of_stream: bool) -> Vec<OggPacket> {
+		let mut ret = Vec::<OggPacket>::new();
+		let mut buf = logical_packet;
+		loop {
+			let packet_type = if self.packet_index == 0 {OggPacketType::BeginOfStream} else {OggPacketType::Continuation};
+			let mut packet = OggPacket::new(self.stream_id, packet_type, self.packet_index);
+			let written = packet.append_page_data(buf, !ret.is_empty());
+			buf = &buf[written..];
+			self.packet_index += 1;
+			let finished = buf.is_empty();
+			if finished {
+				packet.granule_position = granule_position;
+				if is_end_of_stream {
+					packet.set_flags(packet.flags() | PageFlags::END_OF_STREAM);
+				}
+			} else {
+				packet.granule_position = u64::MAX;
+			}
+			packet.recompute_checksum();
+			ret.push(packet);
+			if finished {
+				break;
+			}
+		}
+		ret
+	}
+}
+
 /// * An ogg packet reader
 pub struct OggStreamReader<R>
 where
@@ -324,12 +1270,79 @@ where
 
 	/// * The cached bytes for next read
 	cached_bytes: Vec<u8>,
+
+	/// * How many bytes at the beginning of `cached_bytes` were consumed, they are discarded at the next refill
+	cached_offset: usize,
+
+	/// * The `packet_index` of the last packet read out, for appenders to pick up where the stream left off
+	last_packet_index: Option<u32>,
+
+	/// * If set, report jumps of the `packet_index` of each stream as `OggError::SequenceGap`
+	detect_gaps: bool,
+
+	/// * If set, report the decrease of the granule position of each stream as `OggError::GranuleRegression`
+	detect_granule_regression: bool,
+
+	/// * If set, the violations of the specification become errors, see `set_strict()`
+	strict: bool,
+
+	/// * If the next packet was checked, the next calls return its `pending_errors` and then the packet without checking it again
+	check_reported: bool,
+
+	/// * The violations found in the next packet which are not returned yet
+	pending_errors: VecDeque<OggError>,
+
+	/// * The `packet_index` of the last packet of each stream, for the gap detection
+	last_sequences: HashMap<u32, u32>,
+
+	/// * If each stream seen reached its EOS packet, for the BOS checks of the strict mode
+	ended_streams: HashMap<u32, bool>,
+
+	/// * The last granule position of each stream, for the granule regression detection
+	last_granules: HashMap<u32, u64>,
+
+	/// * How many bytes of packets were consumed, it's the byte offset of the next packet in the reader
+	position: u64,
+
+	/// * The packet parsed by `peek_packet()` along with its offset, to be returned by the next `get_packet()`
+	peeked: Option<(u64, OggPacket)>,
+
+	/// * How many bytes to read at least from the reader for each refill of the cache
+	read_size: usize,
+
+	/// * The max size of the unparsed bytes in the cache, to prevent unbounded allocation on hostile input
+	max_buffer_bytes: usize,
+
+	/// * The unfinished logical packet of each stream along with the packet indices carrying it, waiting for the next packets to continue it
+	partial_packets: HashMap<u32, (Vec<u8>, Vec<u32>)>,
+
+	/// * The logical packets finished by the last packet, to be returned by `get_logical_packet()`
+	finished_packets: VecDeque<OggLogicalPacket>,
+
+	/// * If set by `with_stream_id()`, the packets of the other streams are skipped
+	only_stream: Option<u32>,
+
+	/// * If set, the bytes before the first valid packet are skipped, see `set_skip_leading_junk()`
+	skip_leading_junk: bool,
+
+	/// * If a valid packet was found, the leading junk is only skipped before it
+	synced: bool,
+
+	/// * If set, the reader keeps reading after an EOS packet, see `set_continue_after_eos()`
+	continue_after_eos: bool,
+
+	/// * If set, the packets with a nonzero version are accepted unless in strict mode, see `set_accept_any_version()`
+	accept_any_version: bool,
+
+	/// * The capture pattern of the packets, see `set_capture_pattern()`
+	capture_pattern: [u8; 4],
 }
 
 impl<R> OggStreamReader<R>
 where
 	R: Read + Debug {
 	const READ_SIZE: usize = 2048;
+	const MAX_BUFFER_BYTES: usize = 1 << 20;
 
 	pub fn new(reader: R) -> Self {
 		Self {
@@ -338,7 +1351,176 @@ where
 			e_o_s: false,
 			e_o_f: false,
 			cached_bytes: Vec::new(),
+			cached_offset: 0,
+			last_packet_index: None,
+			detect_gaps: false,
+			detect_granule_regression: false,
+			strict: false,
+			check_reported: false,
+			pending_errors: VecDeque::new(),
+			last_sequences: HashMap::new(),
+			ended_streams: HashMap::new(),
+			last_granules: HashMap::new(),
+			position: 0,
+			peeked: None,
+			read_size: Self::READ_SIZE,
+			max_buffer_bytes: Self::MAX_BUFFER_BYTES,
+			partial_packets: HashMap::new(),
+			finished_packets: VecDeque::new(),
+			only_stream: None,
+			skip_leading_junk: false,
+			synced: false,
+			continue_after_eos: false,
+			accept_any_version: false,
+			capture_pattern: OggPacket::CAPTURE_PATTERN,
+		}
+	}
+
+	/// * Create a reader of a known stream, the packets of the other streams are skipped, so only the EOS packet of this stream ends the stream.
+	/// * The `stream_id` field is set to `stream_id` before any packet is read.
+	pub fn with_stream_id(reader: R, stream_id: u32) -> Self {
+		let mut ret = Self::new(reader);
+		ret.stream_id = stream_id;
+		ret.only_stream = Some(stream_id);
+		ret
+	}
+
+	/// * Set how many bytes to read from the reader for each refill of the cache, the default is 2048, and it's at least 27 which is the minimum size of a packet.
+	/// * A larger size reduces the read calls for high-latency readers such as network streams, a smaller size returns the packets sooner for low-latency readers.
+	pub fn set_read_size(&mut self, read_size: usize) {
+		self.read_size = read_size.max(27);
+	}
+
+	/// * Set the max size of the unparsed bytes in the cache, the default is 1 MiB. When a packet needs more bytes than it, `OggError::BufferOverflow` is returned.
+	/// * The largest valid packet is `OggPacket::MAX_PAGE_SIZE` (65307) bytes, a lower limit rejects some valid packets.
+	pub fn set_max_buffer_bytes(&mut self, max_buffer_bytes: usize) {
+		self.max_buffer_bytes = max_buffer_bytes;
+	}
+
+	/// * Enable or disable the packet sequence gap detection. It's disabled by default.
+	/// * When a gap is detected, `get_packet()` returns an `OggError::SequenceGap` error, and the next call returns the packet after the gap, or the other errors of it.
+	/// * A BOS packet restarts the sequence of its stream, so chained streams won't be reported.
+	pub fn set_detect_gaps(&mut self, detect_gaps: bool) {
+		self.detect_gaps = detect_gaps;
+		self.last_sequences.clear();
+	}
+
+	/// * Enable or disable the granule position regression detection. It's disabled by default.
+	/// * When the granule position of a stream decreases, `get_packet()` returns an `OggError::GranuleRegression` error, and the next call returns the packet, or the other errors of it.
+	/// * The granule position -1 of the packets which don't finish any data is ignored, and a BOS packet restarts the granule position of its stream.
+	pub fn set_detect_granule_regression(&mut self, detect_granule_regression: bool) {
+		self.detect_granule_regression = detect_granule_regression;
+		self.last_granules.clear();
+	}
+
+	/// * Enable or disable the strict mode. It's disabled by default, which tolerates the following violations.
+	/// * In strict mode, these violations become errors:
+	///   * The `packet_index` of a stream doesn't increase by one: `OggError::SequenceRegression` or `OggError::SequenceGap`
+	///   * The EOS packet ends with a segment of 255, or a packet with a granule position doesn't finish any data: `OggError::UnterminatedPacket`
+	///   * The reserved bits 3 to 7 of the header type are set, or a BOS packet has the continued flag: `OggError::BadPacketType`, they are ignored otherwise
+	///   * The first packet of a stream isn't a BOS packet: `OggError::MissingBos`. So a stream must be read from its beginning in strict mode.
+	///   * A BOS packet appears after the other packets of a stream before its EOS packet: `OggError::BosNotFirst`
+	///   * The version isn't zero: `OggError::BadVersion`, even if `set_accept_any_version()` is enabled
+	/// * Every check is done on each packet, the violations of a packet are returned one by one by the following calls, then the next call returns the packet without checking it again.
+	pub fn set_strict(&mut self, strict: bool) {
+		self.strict = strict;
+		self.last_sequences.clear();
+		self.ended_streams.clear();
+	}
+
+	/// * Enable or disable skipping the leading junk before the first packet, e.g. the ID3 tags prepended to the file. It's disabled by default.
+	/// * The reader scans for the first `OggS` capture pattern followed by a packet with a valid checksum, the bytes before it are skipped and counted by `stream_position()`.
+	/// * Only the bytes before the first valid packet are skipped, the later errors are returned as usual.
+	pub fn set_skip_leading_junk(&mut self, skip_leading_junk: bool) {
+		self.skip_leading_junk = skip_leading_junk;
+	}
+
+	/// * Set if the reader keeps reading after an EOS packet. It's disabled by default, `get_packet()` returns `None` after the EOS packet.
+	/// * When enabled, the reader reads on into the chained streams after the EOS packet, and the `stream_id` field is updated by the next BOS packet.
+	///   Then only the end of the input ends the reading, and `is_eos()` tells if the last packet read out is an EOS packet.
+	pub fn set_continue_after_eos(&mut self, continue_after_eos: bool) {
+		self.continue_after_eos = continue_after_eos;
+	}
+
+	/// * Set if the packets with a nonzero version are accepted, for the files of a future version or oddball muxers. It's disabled by default, they are `OggError::BadVersion`.
+	/// * The version of the packet is kept in its `version` field. In strict mode, a nonzero version is always rejected.
+	pub fn set_accept_any_version(&mut self, accept_any_version: bool) {
+		self.accept_any_version = accept_any_version;
+	}
+
+	/// * Set the capture pattern of the packets, the default is `OggS`. For the containers built on the Ogg framing with a custom magic, see `OggPacket::to_bytes_with_capture_pattern()`.
+	/// * The other fields and the checksum are the same as Ogg, the leading junk is skipped to the custom capture pattern.
+	pub fn set_capture_pattern(&mut self, capture_pattern: [u8; 4]) {
+		self.capture_pattern = capture_pattern;
+	}
+
+	fn check_header_type(&self, header_type: u8) -> Result<(), OggError> {
+		let is_continued_bos = PageFlags(header_type).contains(PageFlags::BEGIN_OF_STREAM | PageFlags::CONTINUED);
+		if self.strict && (header_type & PageFlags::RESERVED.bits() != 0 || is_continued_bos) {
+			return Err(OggError::BadPacketType(header_type));
+		}
+		Ok(())
+	}
+
+	/// * The state is updated even if the check fails, so a violation is only reported once.
+	fn check_bos(&mut self, packet: &OggPacket) -> Result<(), OggError> {
+		let ended = self.ended_streams.insert(packet.stream_id, packet.is_last_page());
+		match ended {
+			None | Some(true) if !packet.is_first_page() => Err(OggError::MissingBos{stream_id: packet.stream_id, packet_index: packet.packet_index}),
+			Some(false) if packet.is_first_page() => Err(OggError::BosNotFirst{stream_id: packet.stream_id, packet_index: packet.packet_index}),
+			_ => Ok(()),
+		}
+	}
+
+	/// * Run all of the enabled checks, so that the state of every check is updated and one violation doesn't hide another, returns all of the violations.
+	fn check_packet(&mut self, packet: &OggPacket, header_type: u8) -> Vec<OggError> {
+		let mut errors = Vec::<OggError>::new();
+		errors.extend(self.check_header_type(header_type).err());
+		if self.strict {
+			errors.extend(self.check_bos(packet).err());
+		}
+		if self.detect_gaps || self.strict {
+			errors.extend(self.check_sequence(packet).err());
+		}
+		if self.detect_granule_regression {
+			errors.extend(self.check_granule(packet).err());
+		}
+		if self.strict {
+			let finishes_data = packet.segment_table.iter().any(|&s|s < 255);
+			let must_finish = packet.is_last_page() || packet.granule_position != u64::MAX;
+			if must_finish && !finishes_data && !(packet.segment_table.is_empty() && packet.is_last_page()) {
+				errors.push(OggError::UnterminatedPacket{stream_id: packet.stream_id, packet_index: packet.packet_index});
+			}
+		}
+		errors
+	}
+
+	fn check_sequence(&mut self, packet: &OggPacket) -> Result<(), OggError> {
+		let last = self.last_sequences.insert(packet.stream_id, packet.packet_index);
+		if !packet.is_first_page()
+			&& let Some(last) = last {
+			let expected = last.wrapping_add(1);
+			if self.strict && packet.packet_index <= last {
+				return Err(OggError::SequenceRegression{stream_id: packet.stream_id, prev: last, got: packet.packet_index});
+			} else if packet.packet_index != expected {
+				return Err(OggError::SequenceGap{stream_id: packet.stream_id, expected, got: packet.packet_index});
+			}
+		}
+		Ok(())
+	}
+
+	fn check_granule(&mut self, packet: &OggPacket) -> Result<(), OggError> {
+		if packet.is_first_page() {
+			self.last_granules.remove(&packet.stream_id);
+		}
+		if packet.granule_position == u64::MAX {
+			return Ok(());
+		}
+		if let Some(prev) = self.last_granules.insert(packet.stream_id, packet.granule_position)
+			&& packet.granule_position < prev {
+			return Err(OggError::GranuleRegression{stream_id: packet.stream_id, prev, got: packet.granule_position});
 		}
+		Ok(())
 	}
 
 	fn safe_read(&mut self, target_len: usize) -> io::Result<Vec<u8>> {
@@ -370,210 +1552,3245 @@ where
 		Ok(buf)
 	}
 
-	pub fn get_packet(&mut self) -> io::Result<Option<OggPacket>> {
-		let mut packet_length = 0usize;
-		match OggPacket::from_bytes(&self.cached_bytes, &mut packet_length) {
-			Ok(packet) => {
-				if packet.packet_type == OggPacketType::EndOfStream {
-					self.e_o_s = true;
-				} else {
-					self.e_o_s = false;
-				}
-				self.cached_bytes = self.cached_bytes[packet_length..].to_vec();
-				Ok(Some(packet))
+	fn read_packet(&mut self) -> io::Result<Option<OggPacket>> {
+		loop {
+			if self.e_o_s && !self.continue_after_eos {
+				return Ok(None);
 			}
-			Err(e) => match e.kind() {
-				io::ErrorKind::UnexpectedEof => { // Not enough bytes for an Ogg packet
-					if self.e_o_s {
-						Ok(None)
-					} else {
-						let to_read = max(packet_length, Self::READ_SIZE);
-						let read = self.safe_read(to_read)?;
-						self.cached_bytes.extend(&read);
-						if read.len() < to_read {
-							if self.e_o_f == false {
-								self.e_o_f = true;
-								self.get_packet()
-							} else {
-								if read.len() == 0 {
-									Ok(None)
-								} else {
-									Err(e)
-								}
-							}
+			let mut packet_length = 0usize;
+			let any_version = self.accept_any_version && !self.strict;
+			match OggPacket::parse_with(&self.cached_bytes[self.cached_offset..], &mut packet_length, any_version, self.capture_pattern) {
+				Ok(packet) => {
+					if self.only_stream.is_some_and(|id|id != packet.stream_id) {
+						// The packets of the other streams are skipped without checking them.
+						self.position += packet_length as u64;
+						self.cached_offset += packet_length;
+						continue;
+					}
+					self.synced = true;
+					// The packet stays in the cache until all of its violations are returned, then the next call returns it.
+					if !self.check_reported {
+						let header_type = self.cached_bytes[self.cached_offset + 5];
+						let errors = self.check_packet(&packet, header_type);
+						self.pending_errors.extend(errors);
+						self.check_reported = true;
+					}
+					if let Some(e) = self.pending_errors.pop_front() {
+						return Err(e.into());
+					}
+					self.check_reported = false;
+					self.e_o_s = packet.is_last_page();
+					if packet.is_first_page() {
+						self.stream_id = packet.stream_id;
+					}
+					self.last_packet_index = Some(packet.packet_index);
+					self.position += packet_length as u64;
+					self.cached_offset += packet_length;
+					return Ok(Some(packet));
+				}
+				Err(OggError::UnexpectedEof{got, needed}) => { // Not enough bytes for an Ogg packet
+					if self.e_o_f {
+						// No more bytes will come, it's either a clean end at a packet boundary or a truncated packet.
+						if got == 0 {
+							return Ok(None);
 						} else {
-							self.get_packet()
+							return Err(OggError::Truncated{got, needed}.into());
 						}
 					}
+					if needed > self.max_buffer_bytes {
+						return Err(OggError::BufferOverflow{limit: self.max_buffer_bytes, needed}.into());
+					}
+					// `needed` is how many bytes the packet needs at least as far as its header tells, even if the segment table is incomplete.
+					// Reading that many bytes gets the missing bytes at once, the bytes already cached are read ahead for the next packet.
+					let to_read = needed.max(self.read_size).min(self.max_buffer_bytes.saturating_sub(got)).max(1);
+					let read = self.safe_read(to_read)?;
+					// Only compact the cache when refilling it, the partial packet at the end is moved to the beginning.
+					self.cached_bytes.drain(..self.cached_offset);
+					self.cached_offset = 0;
+					self.cached_bytes.extend(&read);
+					if read.len() < to_read {
+						self.e_o_f = true;
+					}
+				}
+				Err(e @ (OggError::BadCapturePattern(_) | OggError::BadVersion(_) | OggError::ChecksumMismatch{..})) if self.skip_leading_junk && !self.synced => {
+					// Skip to the next capture pattern, but keep the last 3 bytes which may begin one.
+					let data = &self.cached_bytes[self.cached_offset..];
+					let skip = match data[1..].windows(4).position(|w|w == self.capture_pattern) {
+						Some(next) => 1 + next,
+						None if self.e_o_f => return Err(e.into()),
+						None => data.len().saturating_sub(3).max(1),
+					};
+					self.position += skip as u64;
+					self.cached_offset += skip;
 				}
-				_ => Err(e)
+				Err(e @ OggError::ChecksumMismatch{..}) => {
+					// Skip the corrupt packet, so the next call continues from the packet after it.
+					let e = e.at_offset(self.position);
+					self.position += packet_length as u64;
+					self.cached_offset += packet_length;
+					return Err(e.into());
+				}
+				Err(e) => return Err(e.into()),
 			}
 		}
 	}
 
-	pub fn is_eos(&self) -> bool {
-		self.e_o_s
+	pub fn get_packet(&mut self) -> io::Result<Option<OggPacket>> {
+		Ok(self.get_packet_with_offset()?.map(|(_, packet)|packet))
 	}
 
-	pub fn is_eof(&self) -> bool {
-		self.e_o_f
+	/// * Get a packet along with the byte offset in the reader where the packet begins
+	pub fn get_packet_with_offset(&mut self) -> io::Result<Option<(u64, OggPacket)>> {
+		if let Some(peeked) = self.peeked.take() {
+			return Ok(Some(peeked));
+		}
+		// The skipped bytes before the packet are counted, so the offset is taken from the end of the packet.
+		Ok(self.read_packet()?.map(|packet|(self.position - packet.total_page_size() as u64, packet)))
 	}
-}
-
-
-/// * An ogg packets writer sink
-pub struct OggStreamWriter<W>
-where
-	W: Write + Debug {
-	/// * The writer, when a packet is full or you want to seal the packet, the packet is flushed in the writer
-	pub writer: W,
 
-	/// * The unique stream ID for a whole stream. Programs use the stream ID to identify which packet is for which stream.
-	pub stream_id: u32,
+	/// * Parse the next packet without consuming it, the next `get_packet()` returns the same packet.
+	pub fn peek_packet(&mut self) -> io::Result<Option<&OggPacket>> {
+		if self.peeked.is_none() {
+			self.peeked = self.read_packet()?.map(|packet|(self.position - packet.total_page_size() as u64, packet));
+		}
+		Ok(self.peeked.as_ref().map(|(_, packet)|packet))
+	}
 
-	/// * The packet index.
-	pub packet_index: u32,
+	/// * Get the byte offset of the next packet in the reader.
+	/// * The offset counts from where the reader was when it was given to the `OggStreamReader`, bytes cached but not parsed yet are not counted.
+	pub fn stream_position(&self) -> u64 {
+		match &self.peeked {
+			Some((offset, _)) => *offset,
+			None => self.position,
+		}
+	}
 
-	/// * The current packet, ready to be written.
-	pub cur_packet: OggPacket,
+	pub fn is_eos(&self) -> bool {
+		self.e_o_s
+	}
 
-	/// * The granule position is for the programmers to reference it for some purpose.
-	pub granule_position: u64,
+	pub fn is_eof(&self) -> bool {
+		self.e_o_f
+	}
 
-	/// * The `OggStreamWriter<W>` implements `Write`, when the `cur_packet` is full, the `on_seal()` closure will be called for updating the granule position.
-	/// * And then the packet will be flushed into the writer.
-	pub on_seal: Box<dyn FnMut(usize) -> u64>,
+	/// * Get the raw `packet_index` of the last packet read out, `None` if no packet was read.
+	/// * When appending to an existing stream, continue from this index plus one.
+	pub fn get_last_packet_index(&self) -> Option<u32> {
+		self.last_packet_index
+	}
 
-	/// * How many bytes were written into this stream.
-	pub bytes_written: u64,
-}
+	/// * Get a logical packet reassembled from the segments of the packets, which could span multiple packets. Returns `None` at the end of the stream.
+	/// * The logical packets of different streams are separated by the `stream_id`. An unfinished logical packet at the end of the stream is discarded.
+	/// * If a packet doesn't continue the unfinished logical packet of its stream, the unfinished one is discarded as lost, and vice versa.
+	/// * Don't mix it with `get_packet()`, the packets read out by `get_packet()` aren't reassembled.
+	pub fn get_logical_packet(&mut self) -> io::Result<Option<OggLogicalPacket>> {
+		while self.finished_packets.is_empty() {
+			let packet = match self.get_packet()? {
+				Some(packet) => packet,
+				None => return Ok(None),
+			};
+			self.reassemble(&packet);
+		}
+		Ok(self.finished_packets.pop_front())
+	}
 
-impl<W> OggStreamWriter<W>
-where
-	W: Write + Debug {
-	pub fn new(writer: W, stream_id: u32) -> Self {
-		Self {
-			writer,
-			stream_id,
-			packet_index : 0,
-			cur_packet: OggPacket::new(stream_id, OggPacketType::BeginOfStream, 0),
-			granule_position: 0,
-			bytes_written: 0,
-			on_seal: Box::new(|i|i as u64),
+	/// * Reassemble the segments of the packet, the logical packets finished by it are queued in `finished_packets`.
+	fn reassemble(&mut self, packet: &OggPacket) {
+		let mut partial = self.partial_packets.remove(&packet.stream_id);
+		if !packet.is_continued {
+			partial = None;
+		}
+		// The segments continuing a lost logical packet are skipped.
+		let mut skipping = packet.is_continued && partial.is_none();
+		let (mut cur, mut cur_pages) = partial.unwrap_or_default();
+		let mut pos = 0usize;
+		let first_index = self.finished_packets.len();
+		for &size in packet.segment_table.iter() {
+			let next_pos = pos + size as usize;
+			if !skipping {
+				cur.extend(&packet.data[pos..next_pos]);
+				if cur_pages.last() != Some(&packet.packet_index) {
+					cur_pages.push(packet.packet_index);
+				}
+			}
+			pos = next_pos;
+			if size < 255 {
+				if !skipping {
+					self.finished_packets.push_back(OggLogicalPacket {
+						stream_id: packet.stream_id,
+						data: mem::take(&mut cur),
+						granule_position: u64::MAX,
+						is_first_packet: packet.is_first_page() && self.finished_packets.len() == first_index,
+						is_last_packet: false,
+						page_sequences: mem::take(&mut cur_pages),
+					});
+				}
+				skipping = false;
+			}
+		}
+		if !cur.is_empty() {
+			self.partial_packets.insert(packet.stream_id, (cur, cur_pages));
+		}
+		// Only the last logical packet finished in the packet has the granule position of the packet.
+		if self.finished_packets.len() > first_index {
+			let last = self.finished_packets.back_mut().unwrap();
+			last.granule_position = packet.granule_position;
+			last.is_last_packet = packet.is_last_page();
 		}
 	}
 
-	/// * Set the granule position. This field of data is not used by the Ogg stream.
-	/// * The granule position is for the inner things to reference it for some purpose.
-	pub fn set_granule_position(&mut self, position: u64) {
-		self.granule_position = position
+	/// * Iterate the logical packets by `get_logical_packet()` along with the packet indices of the Ogg packets they spanned.
+	/// * A tag editor knows which packets to regenerate when a logical packet changes its size. The iteration ends at the end of the stream or after an error.
+	pub fn packets_with_pages(&mut self) -> impl Iterator<Item = io::Result<(Vec<u8>, Vec<u32>)>> + '_ {
+		let mut failed = false;
+		std::iter::from_fn(move || {
+			if failed {
+				return None;
+			}
+			match self.get_logical_packet() {
+				Ok(packet) => packet.map(|packet|Ok((packet.data, packet.page_sequences))),
+				Err(e) => {
+					failed = true;
+					Some(Err(e))
+				}
+			}
+		})
 	}
 
-	/// * Get the granule position you had set before
-	pub fn get_granule_position(&self) -> u64 {
-		self.granule_position
+	/// * Read and discard the packets until the first packet of the stream `stream_id`, returns false if the input ended before it.
+	/// * The matching packet isn't consumed, the next `get_packet()` returns it. The EOS packets of the other streams don't stop the skipping.
+	pub fn skip_to_stream(&mut self, stream_id: u32) -> io::Result<bool> {
+		loop {
+			if self.peeked.is_none() {
+				self.e_o_s = false;
+			}
+			match self.peek_packet()? {
+				Some(packet) if packet.stream_id == stream_id => return Ok(true),
+				Some(_) => self.peeked = None,
+				None => return Ok(false),
+			}
+		}
 	}
 
-	/// * Mark the current packet as EOS
-	pub fn mark_cur_packet_as_end_of_stream(&mut self) {
-		self.cur_packet.packet_type = OggPacketType::EndOfStream;
+	/// * Get the next packet of the stream `stream_id`, the packets of the other streams are skipped, and their EOS packets don't end the input.
+	fn get_packet_of_stream(&mut self, stream_id: u32) -> io::Result<Option<OggPacket>> {
+		loop {
+			match self.get_packet()? {
+				Some(packet) if packet.stream_id == stream_id => return Ok(Some(packet)),
+				// The EOS packet of another stream doesn't end the input.
+				Some(_) => self.e_o_s = false,
+				None => return Ok(None),
+			}
+		}
 	}
 
-	/// * Get how many bytes written in this stream
-	pub fn get_bytes_written(&self) -> u64 {
-		self.bytes_written
+	/// * Create a reader which only returns the packets of the stream `stream_id`, see `OggStreamFilter`.
+	pub fn filter_stream(reader: R, stream_id: u32) -> OggStreamFilter<R> {
+		OggStreamFilter::new(reader, stream_id)
 	}
 
-	/// * Set a callback for the `Write` trait when it seals the packet, the callback helps with updating the granule position
-	pub fn set_on_seal_callback(&mut self, on_seal: Box<dyn FnMut(usize) -> u64>) {
-		self.on_seal = on_seal;
+	/// * Get a `Read` adapter which reads the concatenated data of the packets of a single stream, the packets are read as the data is consumed.
+	/// * The stream is the one of the next packet, the packets of the other streams are skipped. It reaches EOF after the EOS packet of the stream.
+	pub fn payload_reader(&mut self) -> OggPayloadReader<'_, R> {
+		OggPayloadReader {
+			reader: self,
+			stream_id: None,
+			data: Vec::new(),
+			data_offset: 0,
+			finished: false,
+		}
 	}
 
-	/// * Reset the stream state, discard the packet, reinit the packet to a BOS
-	pub fn reset(&mut self) {
-		self.packet_index = 0;
-		self.cur_packet = OggPacket::new(self.stream_id, OggPacketType::BeginOfStream, 0);
-		self.granule_position = 0;
-		self.bytes_written = 0;
+	/// * Clear the EOS and EOF state and the `stream_id`, so that the reader can be reused to read the following chained stream from the same reader.
+	/// * The cached bytes are kept, the next `get_packet()` continues from them and picks up the `stream_id` from the next BOS packet.
+	pub fn reset_stream_state(&mut self) {
+		self.e_o_s = false;
+		self.e_o_f = false;
+		self.stream_id = self.only_stream.unwrap_or(0);
 	}
 
-	/// * Save the current packet and write it to the sink, then create a new packet for writing.
-	pub fn seal_packet(&mut self, granule_position: u64, is_end_of_stream: bool) -> io::Result<()> {
-		self.packet_index += 1;
-		self.granule_position = granule_position;
-		self.cur_packet.granule_position = self.granule_position;
-		let packed = if is_end_of_stream {
-			self.cur_packet.packet_type = OggPacketType::EndOfStream;
-			mem::take(&mut self.cur_packet).into_bytes()
-		} else {
-			mem::replace(&mut self.cur_packet, OggPacket::new(self.stream_id, OggPacketType::Continuation, self.packet_index)).into_bytes()
+	/// * Discard the cached bytes and the state of the streams after the underlying reader was repositioned to `position`, which is the offset of the next packet.
+	fn discard_buffered(&mut self, position: u64) {
+		self.e_o_s = false;
+		self.e_o_f = false;
+		self.cached_bytes.clear();
+		self.cached_offset = 0;
+		self.check_reported = false;
+		self.pending_errors.clear();
+		self.last_sequences.clear();
+		self.ended_streams.clear();
+		self.last_granules.clear();
+		self.position = position;
+		self.peeked = None;
+		self.partial_packets.clear();
+		self.finished_packets.clear();
+	}
+
+	/// * Get back the underlying reader along with the cached bytes, which were already read from the reader but not parsed into packets yet.
+	/// * The cached bytes come before the reader's current position, process them first to continue from where the `OggStreamReader` stopped.
+	/// * A packet parsed by `peek_packet()` isn't consumed, its raw bytes are at the beginning of the cached bytes as they were read.
+	pub fn into_inner(self) -> (R, Vec<u8>) {
+		// Nothing is read into the cache while a packet is peeked, so the peeked packet is still right before `cached_offset`.
+		let begin = match &self.peeked {
+			Some((_, packet)) => self.cached_offset - packet.total_page_size(),
+			None => self.cached_offset,
 		};
-		self.writer.write_all(&packed)?;
-		Ok(())
+		(self.reader, self.cached_bytes[begin..].to_vec())
 	}
 }
 
-impl<W> Write for OggStreamWriter<W>
+/// * A `Read` adapter over the data of the packets of a single stream, created by `OggStreamReader::payload_reader()`
+pub struct OggPayloadReader<'a, R>
 where
-	W: Write + Debug {
-	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-		self.bytes_written = buf.len() as u64;
-		let mut buf = buf;
-		let mut written_total = 0usize;
-		while !buf.is_empty() {
-			let written = self.cur_packet.write(buf);
-			buf = &buf[written..];
-			written_total += written;
-			if !buf.is_empty() {
-				self.granule_position = (self.on_seal)(self.cur_packet.get_inner_data_size());
-				self.seal_packet(self.granule_position, false)?;
+	R: Read + Debug {
+	reader: &'a mut OggStreamReader<R>,
+
+	/// * The stream to read, it's the stream of the first packet read
+	stream_id: Option<u32>,
+
+	/// * The data of the current packet
+	data: Vec<u8>,
+
+	/// * How many bytes of the `data` were read
+	data_offset: usize,
+
+	/// * If the EOS packet of the stream was read, or there are no more packets
+	finished: bool,
+}
+
+impl<R> Read for OggPayloadReader<'_, R>
+where
+	R: Read + Debug {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		while self.data_offset >= self.data.len() {
+			if self.finished {
+				return Ok(0);
 			}
+			let packet = match self.stream_id {
+				Some(stream_id) => self.reader.get_packet_of_stream(stream_id)?,
+				None => self.reader.get_packet()?,
+			};
+			let packet = match packet {
+				Some(packet) => packet,
+				None => {
+					self.finished = true;
+					return Ok(0);
+				}
+			};
+			self.stream_id = Some(packet.stream_id);
+			self.finished = packet.is_last_page();
+			self.data = packet.data;
+			self.data_offset = 0;
 		}
-		Ok(written_total)
+		let size = buf.len().min(self.data.len() - self.data_offset);
+		buf[..size].copy_from_slice(&self.data[self.data_offset..self.data_offset + size]);
+		self.data_offset += size;
+		Ok(size)
 	}
+}
 
-	fn flush(&mut self) -> io::Result<()> {
-		self.writer.flush()
+/// * A reader which only returns the packets of a single stream from a multiplexed physical stream, created by `OggStreamReader::filter_stream()`.
+/// * The packets of the other streams are parsed and dropped immediately, so the memory stays flat no matter how many streams there are.
+/// * Their EOS packets don't stop the reading, it returns `None` after the EOS packet of the stream or at the end of the input.
+pub struct OggStreamFilter<R>
+where
+	R: Read + Debug {
+	/// * The underlying reader
+	pub reader: OggStreamReader<R>,
+
+	/// * The stream to return the packets of
+	pub stream_id: u32,
+
+	/// * If the EOS packet of the stream was returned
+	finished: bool,
+}
+
+impl<R> OggStreamFilter<R>
+where
+	R: Read + Debug {
+	pub fn new(reader: R, stream_id: u32) -> Self {
+		Self {
+			reader: OggStreamReader::new(reader),
+			stream_id,
+			finished: false,
+		}
+	}
+
+	/// * Get the next packet of the stream, the packets of the other streams are skipped.
+	pub fn get_packet(&mut self) -> io::Result<Option<OggPacket>> {
+		if self.finished {
+			return Ok(None);
+		}
+		let packet = self.reader.get_packet_of_stream(self.stream_id)?;
+		self.finished = packet.as_ref().is_none_or(|packet|packet.is_last_page());
+		Ok(packet)
 	}
 }
 
-impl<W> Debug for OggStreamWriter<W>
+impl<R> Iterator for OggStreamFilter<R>
 where
-	W: Write + Debug {
+	R: Read + Debug {
+	type Item = io::Result<OggPacket>;
+	fn next(&mut self) -> Option<Self::Item> {
+		self.get_packet().transpose()
+	}
+}
+
+impl<R> Debug for OggStreamFilter<R>
+where
+	R: Read + Debug {
 	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-		f.debug_struct(&format!("OggStreamWriter<{}>", std::any::type_name::<W>()))
-		.field("writer", &self.writer)
+		f.debug_struct("OggStreamFilter")
+		.field("reader", &self.reader.reader)
 		.field("stream_id", &format_args!("0x{:08x}", self.stream_id))
-		.field("packet_index", &self.packet_index)
-		.field("cur_packet", &self.cur_packet)
-		.field("granule_position", &self.granule_position)
-		.field("on_seal", &format_args!("<closure>"))
-		.field("bytes_written", &self.bytes_written)
+		.field("finished", &self.finished)
 		.finish()
 	}
 }
 
-impl<W> Drop for OggStreamWriter<W>
+/// * Split the packets of a stream into its header packets and its data packets in one pass, the packets of the other streams are skipped.
+/// * The header packets are the packets before the first packet with a granule position other than 0 and -1. The packets with the granule position -1 are held until a later packet tells which part they belong to.
+/// * The iterator gives the data packets until the EOS packet of the stream or the end of the input.
+pub fn partition_stream<R>(reader: &mut OggStreamReader<R>, stream_id: u32) -> io::Result<(Vec<OggPacket>, impl Iterator<Item = io::Result<OggPacket>> + '_)>
 where
-	W: Write + Debug {
-	fn drop(&mut self) {
-		self.seal_packet(self.granule_position, true).unwrap();
+	R: Read + Debug {
+	let mut finished = false;
+	let mut next_packet = move |reader: &mut OggStreamReader<R>| -> io::Result<Option<OggPacket>> {
+		if finished {
+			return Ok(None);
+		}
+		let packet = reader.get_packet_of_stream(stream_id)?;
+		finished = packet.as_ref().is_none_or(|packet|packet.is_last_page());
+		Ok(packet)
+	};
+	let mut headers = Vec::<OggPacket>::new();
+	let mut undecided = Vec::<OggPacket>::new();
+	while let Some(packet) = next_packet(reader)? {
+		match packet.granule_position {
+			u64::MAX => undecided.push(packet),
+			0 => {
+				headers.append(&mut undecided);
+				headers.push(packet);
+			}
+			_ => {
+				undecided.push(packet);
+				break;
+			}
+		}
 	}
+	let data_packets = undecided.into_iter().map(Ok).chain(std::iter::from_fn(move ||next_packet(reader).transpose()));
+	Ok((headers, data_packets))
 }
 
-#[test]
-fn test_ogg() {
-	use std::{
-		fs::File,
-		io::BufReader,
-	};
-	let mut oggreader = OggStreamReader::new(BufReader::new(File::open("test.ogg").unwrap()));
-	loop {
-		let packet = oggreader.get_packet().unwrap();
-		if let Some(packet) = packet {
-			dbg!(packet);
-		} else {
-			break;
+/// * A logical packet of a codec, reassembled from the segments of the Ogg packets
+#[derive(Debug, Clone, PartialEq)]
+pub struct OggLogicalPacket {
+	/// * The stream ID of the Ogg packets carrying it
+	pub stream_id: u32,
+
+	/// * The data of the logical packet
+	pub data: Vec<u8>,
+
+	/// * The granule position of the Ogg packet where it ends if it's the last logical packet ending there, otherwise -1
+	pub granule_position: u64,
+
+	/// * If it's the first logical packet of the BOS packet
+	pub is_first_packet: bool,
+
+	/// * If it's the last logical packet of the EOS packet
+	pub is_last_packet: bool,
+
+	/// * The `packet_index` of each Ogg packet carrying the segments of it, in order
+	pub page_sequences: Vec<u32>,
+}
+
+/// * A reader which parses the packets directly from the buffer of a `BufRead`, instead of caching the bytes again like `OggStreamReader` does.
+/// * The bytes are only copied when a packet straddles the end of the buffer, so wrapping a `BufReader<File>` doesn't buffer the data twice.
+/// * Only the packets beginning with `OggS` are parsed, use `OggStreamReader::set_capture_pattern()` for a custom capture pattern.
+pub struct OggBufReader<R>
+where
+	R: BufRead + Debug {
+	/// * The reader
+	pub reader: R,
+
+	/// * The unique stream ID, after read out the first packet, this field is set.
+	pub stream_id: u32,
+
+	/// * If an EOS is encountered, this field is set to true
+	e_o_s: bool,
+
+	/// * The beginning of a packet which straddles the end of the buffer, only exactly the bytes of the packet are taken from the reader
+	straddle: Vec<u8>,
+
+	/// * How many bytes of packets were consumed, it's the byte offset of the next packet in the reader
+	position: u64,
+}
+
+impl<R> OggBufReader<R>
+where
+	R: BufRead + Debug {
+	pub fn new(reader: R) -> Self {
+		Self {
+			reader,
+			stream_id: 0,
+			e_o_s: false,
+			straddle: Vec::new(),
+			position: 0,
+		}
+	}
+
+	/// * Fill the buffer, retrying on `ErrorKind::Interrupted`. A second `fill_buf()` after a success returns the same buffer without reading.
+	fn fill_buf(reader: &mut R) -> io::Result<&[u8]> {
+		while let Err(e) = reader.fill_buf() {
+			if e.kind() != ErrorKind::Interrupted {
+				return Err(e);
+			}
+		}
+		reader.fill_buf()
+	}
+
+	fn got_packet(&mut self, packet: OggPacket, packet_length: usize) -> OggPacket {
+		self.e_o_s = packet.is_last_page();
+		if packet.is_first_page() {
+			self.stream_id = packet.stream_id;
+		}
+		self.position += packet_length as u64;
+		packet
+	}
+
+	pub fn get_packet(&mut self) -> io::Result<Option<OggPacket>> {
+		let mut packet_length = 0usize;
+		if self.straddle.is_empty() {
+			let buf = Self::fill_buf(&mut self.reader)?;
+			if buf.is_empty() {
+				return Ok(None);
+			}
+			match OggPacket::parse(buf, &mut packet_length) {
+				Ok(packet) => {
+					self.reader.consume(packet_length);
+					return Ok(Some(self.got_packet(packet, packet_length)));
+				}
+				Err(OggError::UnexpectedEof{..}) => {
+					if self.e_o_s {
+						return Ok(None);
+					}
+					// The packet straddles the end of the buffer, continue with the copied bytes.
+					self.straddle.extend(buf);
+					let consumed = buf.len();
+					self.reader.consume(consumed);
+				}
+				Err(e @ OggError::ChecksumMismatch{..}) => {
+					// Skip the corrupt packet, so the next call continues from the packet after it.
+					let e = e.at_offset(self.position);
+					self.reader.consume(packet_length);
+					self.position += packet_length as u64;
+					return Err(e.into());
+				}
+				Err(e) => return Err(e.into()),
+			}
+		}
+		loop {
+			match OggPacket::parse(&self.straddle, &mut packet_length) {
+				Ok(packet) => {
+					self.straddle.clear();
+					return Ok(Some(self.got_packet(packet, packet_length)));
+				}
+				Err(OggError::UnexpectedEof{got, needed}) => {
+					let buf = Self::fill_buf(&mut self.reader)?;
+					if buf.is_empty() {
+						if self.e_o_s {
+							self.straddle.clear();
+							return Ok(None);
+						}
+						return Err(OggError::Truncated{got, needed}.into());
+					}
+					// Only take the bytes of this packet, the rest stay in the buffer for the next call.
+					let take = (needed - got).min(buf.len());
+					self.straddle.extend(&buf[..take]);
+					self.reader.consume(take);
+				}
+				Err(e @ OggError::ChecksumMismatch{..}) => {
+					let e = e.at_offset(self.position);
+					self.straddle.clear();
+					self.position += packet_length as u64;
+					return Err(e.into());
+				}
+				Err(e) => {
+					// The bytes were already taken from the reader, drop them so the next call doesn't parse them again.
+					self.position += self.straddle.len() as u64;
+					self.straddle.clear();
+					return Err(e.into());
+				}
+			}
+		}
+	}
+
+	/// * Get the byte offset of the next packet in the reader.
+	pub fn stream_position(&self) -> u64 {
+		self.position
+	}
+
+	pub fn is_eos(&self) -> bool {
+		self.e_o_s
+	}
+
+	/// * Get back the reader, along with the bytes of a partial packet which were taken from it.
+	pub fn into_inner(self) -> (R, Vec<u8>) {
+		(self.reader, self.straddle)
+	}
+}
+
+impl<R> Debug for OggBufReader<R>
+where
+	R: BufRead + Debug {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		f.debug_struct("OggBufReader")
+		.field("reader", &self.reader)
+		.field("stream_id", &self.stream_id)
+		.field("e_o_s", &self.e_o_s)
+		.field("straddle", &format_args!("[u8; {}]", self.straddle.len()))
+		.field("position", &self.position)
+		.finish()
+	}
+}
+
+/// * The information of a chained stream, retrieved from its BOS packet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChainInfo {
+	/// * The stream ID of the BOS packet
+	pub stream_id: u32,
+
+	/// * The codec detected from the BOS packet
+	pub codec: Codec,
+}
+
+/// * A reader for chained Ogg streams, which are concatenated logical streams, each with its own BOS ... EOS.
+/// * Call `next_chain()` to advance to the next chained stream, then call `get_packet()` to read its packets until `None`.
+/// * If a chain is multiplexed, the packets of every stream whose BOS appeared in the chain are returned, the chain ends when all of them reached EOS.
+pub struct OggChainReader<R>
+where
+	R: Read + Debug {
+	/// * The underlying reader
+	pub reader: OggStreamReader<R>,
+
+	/// * The BOS packet read by `next_chain()`, to be returned by `get_packet()`
+	pending: Option<OggPacket>,
+
+	/// * The streams of the current chain which haven't reached EOS
+	open_streams: HashSet<u32>,
+
+	/// * If the current chain isn't finished
+	in_chain: bool,
+}
+
+impl<R> OggChainReader<R>
+where
+	R: Read + Debug {
+	pub fn new(reader: R) -> Self {
+		Self {
+			reader: OggStreamReader::new(reader),
+			pending: None,
+			open_streams: HashSet::new(),
+			in_chain: false,
+		}
+	}
+
+	fn read_packet(&mut self) -> io::Result<Option<OggPacket>> {
+		// The underlying reader stops after EOS, but there may be another chain after it.
+		self.reader.e_o_s = false;
+		self.reader.get_packet()
+	}
+
+	/// * Skip the rest of the current chain, advance to the BOS packet of the next chain.
+	/// * Returns `None` if there are no more chains.
+	pub fn next_chain(&mut self) -> io::Result<Option<ChainInfo>> {
+		while self.get_packet()?.is_some() {}
+		while let Some(packet) = self.read_packet()? {
+			if packet.is_first_page() {
+				let info = ChainInfo {
+					stream_id: packet.stream_id,
+					codec: detect_codec(&packet.get_first_segment_run()),
+				};
+				self.open_streams.clear();
+				self.open_streams.insert(packet.stream_id);
+				self.pending = Some(packet);
+				self.in_chain = true;
+				return Ok(Some(info));
+			}
+		}
+		Ok(None)
+	}
+
+	/// * Get a packet of the current chain, returns `None` at the end of the chain.
+	pub fn get_packet(&mut self) -> io::Result<Option<OggPacket>> {
+		if !self.in_chain {
+			return Ok(None);
+		}
+		let packet = match self.pending.take() {
+			Some(packet) => packet,
+			None => match self.read_packet()? {
+				Some(packet) => packet,
+				None => {
+					self.in_chain = false;
+					return Ok(None);
+				}
+			}
+		};
+		if packet.is_first_page() {
+			self.open_streams.insert(packet.stream_id);
+		}
+		if packet.is_last_page() {
+			self.open_streams.remove(&packet.stream_id);
+			if self.open_streams.is_empty() {
+				self.in_chain = false;
+			}
+		}
+		Ok(Some(packet))
+	}
+}
+
+/// * A cheap snapshot of the header of a packet without its data, for logging. It's given by `OggPacket::as_page_info()`, and to the `on_page_written` callback of `OggStreamWriter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageInfo {
+	/// * The stream ID
+	pub stream_id: u32,
+
+	/// * The packet index
+	pub sequence: u32,
+
+	/// * The flags of the header type
+	pub flags: PageFlags,
+
+	/// * The granule position
+	pub granule: u64,
+
+	/// * The size of the data
+	pub payload_len: usize,
+
+	/// * The number of segments in the segment table
+	pub segment_count: usize,
+
+	/// * The size of the serialized packet
+	pub byte_len: usize,
+
+	/// * If it's the EOS packet
+	pub is_eos: bool,
+}
+
+/// * The callback of `OggStreamWriter` called after each packet is written
+pub type OnPageWritten = Box<dyn FnMut(&PageInfo)>;
+
+/// * The paging shared by `OggStreamWriter` and `AsyncOggStreamWriter`, borrowed from the fields of the writer. The writers only differ in how the sealed packets are written to the sink.
+struct Pager<'a> {
+	stream_id: u32,
+	packet_index: &'a mut u32,
+	cur_packet: &'a mut OggPacket,
+	granule_position: &'a mut u64,
+	has_sealed: &'a mut bool,
+	ended: &'a mut bool,
+	max_page_payload: usize,
+	capture_pattern: [u8; 4],
+}
+
+impl Pager<'_> {
+	/// * If the current packet was marked as EOS or the EOS packet was written
+	fn is_ended(&self) -> bool {
+		*self.ended || self.cur_packet.flags().contains(PageFlags::END_OF_STREAM)
+	}
+
+	/// * Returns `OggError::WriteAfterEos` if nothing can be written, so that no data goes into or after the EOS packet.
+	fn check_not_ended(&self) -> io::Result<()> {
+		if self.is_ended() {
+			return Err(OggError::WriteAfterEos{stream_id: self.stream_id}.into());
+		}
+		Ok(())
+	}
+
+	/// * Write the data of the byte stream mode to the current packet, returns the written bytes, 0 if the packet is full and should be sealed.
+	fn fill(&mut self, data: &[u8]) -> usize {
+		self.cur_packet.write_limited(data, self.max_page_payload)
+	}
+
+	/// * Seal the current packet and replace it with the next one, returns the serialized packet to write to the sink and the information of it.
+	/// * The packet is the EOS packet if `is_end_of_stream` is true or it was marked as EOS. After the EOS packet, sealing returns `OggError::WriteAfterEos`.
+	fn seal(&mut self, granule_position: u64, is_end_of_stream: bool) -> io::Result<(Vec<u8>, PageInfo)> {
+		if *self.ended {
+			return Err(OggError::WriteAfterEos{stream_id: self.stream_id}.into());
+		}
+		let is_end_of_stream = is_end_of_stream || self.cur_packet.flags().contains(PageFlags::END_OF_STREAM);
+		*self.ended = is_end_of_stream;
+		*self.packet_index += 1;
+		*self.granule_position = granule_position;
+		*self.has_sealed = true;
+		let sealed = self.cur_packet.seal(granule_position, is_end_of_stream);
+		Ok((sealed.to_bytes_with_capture_pattern(self.capture_pattern), sealed.as_page_info()))
+	}
+}
+
+/// * An ogg packets writer sink
+pub struct OggStreamWriter<W>
+where
+	W: Write + Debug {
+	/// * The writer, when a packet is full or you want to seal the packet, the packet is flushed in the writer
+	pub writer: W,
+
+	/// * The unique stream ID for a whole stream. Programs use the stream ID to identify which packet is for which stream.
+	pub stream_id: u32,
+
+	/// * The packet index.
+	pub packet_index: u32,
+
+	/// * The current packet, ready to be written.
+	pub cur_packet: OggPacket,
+
+	/// * The granule position is for the programmers to reference it for some purpose.
+	pub granule_position: u64,
+
+	/// * The `OggStreamWriter<W>` implements `Write`, when the `cur_packet` is full, the `on_seal()` closure will be called for updating the granule position.
+	/// * And then the packet will be flushed into the writer. If the closure returns an error, the packet isn't sealed, and `write()` returns the size written before it, or the error if nothing was written.
+	pub on_seal: Box<dyn FnMut(usize) -> io::Result<u64>>,
+
+	/// * How many bytes of data were written to the sink in the packets of this stream, the data buffered in the `cur_packet` is counted when the packet is written.
+	pub bytes_written: u64,
+
+	/// * If set, it's called after each packet is written to the sink, e.g. for progress reporting or pushing each packet downstream.
+	pub on_page_written: Option<OnPageWritten>,
+
+	/// * If any packet was sealed and written to the sink
+	has_sealed: bool,
+
+	/// * When the data size of the `cur_packet` reaches it, the packet is sealed, see `set_max_page_payload()`. It's never below 255, or no segment could be written.
+	max_page_payload: usize,
+
+	/// * If set, each `write()` call is written as a logical packet, see `set_packet_per_write()`
+	packet_per_write: bool,
+
+	/// * If the EOS packet was written, nothing can be written after it until `reset()`
+	ended: bool,
+
+	/// * If set, dropping the writer seals the current packet as the EOS packet, see `set_flush_on_drop()`
+	flush_on_drop: bool,
+
+	/// * The capture pattern of the packets, see `set_capture_pattern()`
+	capture_pattern: [u8; 4],
+
+	/// * If set, the packets written by `write_page()` are renumbered to the packet index of the writer, see `set_renumber_pages()`
+	renumber_pages: bool,
+}
+
+impl<W> OggStreamWriter<W>
+where
+	W: Write + Debug {
+	/// * Create a writer of the stream `stream_id`. The stream IDs of the streams in the same physical stream must differ, or the demuxer can't separate them.
+	/// * 0 is a valid stream ID but it's likely to collide with another stream, use `random_stream_id()` unless the ID is given.
+	pub fn new(writer: W, stream_id: u32) -> Self {
+		Self {
+			writer,
+			stream_id,
+			packet_index : 0,
+			cur_packet: OggPacket::new(stream_id, OggPacketType::BeginOfStream, 0),
+			granule_position: 0,
+			bytes_written: 0,
+			on_seal: Box::new(|i|Ok(i as u64)),
+			on_page_written: None,
+			max_page_payload: OggPacket::MAX_PAYLOAD,
+			has_sealed: false,
+			packet_per_write: false,
+			ended: false,
+			flush_on_drop: true,
+			capture_pattern: OggPacket::CAPTURE_PATTERN,
+			renumber_pages: true,
 		}
 	}
+
+	/// * Create a writer which continues an existing stream whose last packet ended in the middle of a logical packet (its last segment is 255).
+	/// * The first packet has the index `packet_index` and the continued flag set, so the first `write_packet()` or `write()` finishes the unfinished logical packet.
+	/// * `granule_position` is the granule position to start from, it's given to the first packet which finishes a logical packet.
+	pub fn resume_mid_packet(writer: W, stream_id: u32, packet_index: u32, granule_position: u64) -> Self {
+		let mut ret = Self::new(writer, stream_id);
+		ret.set_page_sequence(packet_index);
+		ret.cur_packet.set_flags(PageFlags::CONTINUED);
+		ret.granule_position = granule_position;
+		ret
+	}
+
+	/// * Set the granule position. This field of data is not used by the Ogg stream.
+	/// * The granule position is for the inner things to reference it for some purpose.
+	pub fn set_granule_position(&mut self, position: u64) {
+		self.granule_position = position
+	}
+
+	/// * Get the granule position you had set before
+	pub fn get_granule_position(&self) -> u64 {
+		self.granule_position
+	}
+
+	/// * Mark the current packet as EOS, it's written as the EOS packet when it's sealed.
+	/// * After it, writing more data returns `OggError::WriteAfterEos` instead of putting the data after the EOS, call `reset()` to begin a new stream.
+	pub fn mark_cur_packet_as_end_of_stream(&mut self) {
+		self.cur_packet.set_flags(self.cur_packet.flags() | PageFlags::END_OF_STREAM);
+	}
+
+	/// * Get how many bytes of data were written to the sink in this stream, by any of the writing functions. The headers of the packets aren't counted.
+	pub fn get_bytes_written(&self) -> u64 {
+		self.bytes_written
+	}
+
+	/// * Set a callback for the `Write` trait when it seals the packet, the callback helps with updating the granule position
+	pub fn set_on_seal_callback(&mut self, mut on_seal: Box<dyn FnMut(usize) -> u64>) {
+		self.on_seal = Box::new(move |size|Ok(on_seal(size)));
+	}
+
+	/// * Set a callback which is called after each packet is written to the sink, with its packet index, granule position, size and if it's the EOS packet.
+	pub fn set_on_page_written_callback(&mut self, on_page_written: OnPageWritten) {
+		self.on_page_written = Some(on_page_written);
+	}
+
+	/// * Set a callback which could fail, e.g. when the codec state can't give a timestamp. Its error is returned by the `write()` call which seals the packet.
+	/// * The packet isn't sealed when the callback fails, the data stays in it, and the next `write()` calls the callback again.
+	///   A `write()` call which already wrote some data returns its size instead, so the error is returned by the next call, and the data isn't written twice by `write_all()`.
+	pub fn set_try_on_seal_callback(&mut self, on_seal: Box<dyn FnMut(usize) -> io::Result<u64>>) {
+		self.on_seal = on_seal;
+	}
+
+	/// * Set the max data size of a packet, it's clamped between 255 and `OggPacket::MAX_PAYLOAD`.
+	/// * The packets are sealed earlier with a lower size, which reduces the buffering latency for live streaming.
+	/// * Only whole segments are written, so the data size of a packet is rounded down to a multiple of 255 unless it ends the data.
+	pub fn set_max_page_payload(&mut self, max_page_payload: usize) {
+		self.max_page_payload = max_page_payload.clamp(255, OggPacket::MAX_PAYLOAD);
+	}
+
+	/// * Get the max data size of a packet
+	pub fn get_max_page_payload(&self) -> usize {
+		self.max_page_payload
+	}
+
+	/// * Change the stream ID of the stream along with the current packet, e.g. to decide the ID after hashing some metadata.
+	/// * It's only allowed before any packet is sealed, otherwise the stream would have packets of different stream IDs.
+	pub fn set_stream_id(&mut self, stream_id: u32) -> io::Result<()> {
+		if self.has_sealed {
+			return Err(io::Error::new(ErrorKind::InvalidInput, format!("Can't change the stream ID to 0x{stream_id:08x}: some packets of the stream 0x{:08x} were already written", self.stream_id)));
+		}
+		self.stream_id = stream_id;
+		self.cur_packet.stream_id = stream_id;
+		Ok(())
+	}
+
+	/// * Set if each `write()` call is treated as a logical packet, it's disabled by default, which treats the data as a byte stream.
+	/// * In the per-write mode, the data of each `write()` is terminated by a segment shorter than 255 bytes like `write_packet()`, so the decoder can find where each of them ends.
+	///   In the byte stream mode, the data of the calls are merged into the segments without boundaries, e.g. a write of a multiple of 255 bytes isn't terminated, it merges with the next write.
+	/// * The per-write mode doesn't call the `on_seal` callback, the packets have the granule position set by `set_granule_position()` like `write_packet()`.
+	pub fn set_packet_per_write(&mut self, packet_per_write: bool) {
+		self.packet_per_write = packet_per_write;
+	}
+
+	/// * Set the capture pattern of the packets, the default is `OggS`. For the containers built on the Ogg framing with a custom magic, read them with `OggStreamReader::set_capture_pattern()`.
+	/// * The other fields and the checksum are the same as Ogg.
+	pub fn set_capture_pattern(&mut self, capture_pattern: [u8; 4]) {
+		self.capture_pattern = capture_pattern;
+	}
+
+	/// * Set if `write_page()` renumbers the packets to the packet index of the writer, it's enabled by default.
+	/// * When disabled, the packets keep their packet indices, e.g. for copying a stream with its gaps as is, and the packets written after them are numbered from the last one.
+	pub fn set_renumber_pages(&mut self, renumber_pages: bool) {
+		self.renumber_pages = renumber_pages;
+	}
+
+	/// * Set if dropping the writer seals the current packet as the EOS packet and writes it, it's enabled by default.
+	/// * Disable it when the stream is abandoned, e.g. after an error, so that a half-written stream doesn't get an EOS packet stamped on it.
+	pub fn set_flush_on_drop(&mut self, flush_on_drop: bool) {
+		self.flush_on_drop = flush_on_drop;
+	}
+
+	/// * Drop the writer without writing anything, the buffered data in the current packet is discarded and no EOS packet is written.
+	pub fn discard(mut self) {
+		self.flush_on_drop = false;
+	}
+
+	/// * Reset the stream state, discard the packet, reinit the packet to a BOS
+	/// * It also allows writing again after the EOS, the following packets begin a new stream.
+	pub fn reset(&mut self) {
+		self.packet_index = 0;
+		self.cur_packet = OggPacket::new(self.stream_id, OggPacketType::BeginOfStream, 0);
+		self.granule_position = 0;
+		self.bytes_written = 0;
+		self.has_sealed = false;
+		self.ended = false;
+	}
+
+	/// * If the current packet was marked as EOS or the EOS packet was written
+	pub fn is_ended(&self) -> bool {
+		self.ended || self.cur_packet.flags().contains(PageFlags::END_OF_STREAM)
+	}
+
+	/// * Returns `OggError::WriteAfterEos` if nothing can be written, so that no data goes into or after the EOS packet.
+	fn check_not_ended(&mut self) -> io::Result<()> {
+		self.pager().check_not_ended()
+	}
+
+	/// * Borrow the paging state shared with `AsyncOggStreamWriter`
+	fn pager(&mut self) -> Pager<'_> {
+		Pager {
+			stream_id: self.stream_id,
+			packet_index: &mut self.packet_index,
+			cur_packet: &mut self.cur_packet,
+			granule_position: &mut self.granule_position,
+			has_sealed: &mut self.has_sealed,
+			ended: &mut self.ended,
+			max_page_payload: self.max_page_payload,
+			capture_pattern: self.capture_pattern,
+		}
+	}
+
+	/// * Count the data of the packet written to the sink and call the `on_page_written` callback with it
+	fn page_written(&mut self, info: &PageInfo) {
+		self.bytes_written += info.payload_len as u64;
+		if let Some(on_page_written) = self.on_page_written.as_mut() {
+			on_page_written(info);
+		}
+	}
+
+	/// * Set the packet index for the current packet, the following packets are numbered from it.
+	/// * Use this to continue an existing stream. If `start` isn't zero, the current packet is no longer a BOS packet.
+	pub fn set_page_sequence(&mut self, start: u32) {
+		self.packet_index = start;
+		self.cur_packet.packet_index = start;
+		if start != 0 && self.cur_packet.packet_type == OggPacketType::BeginOfStream {
+			self.cur_packet.packet_type = OggPacketType::Continuation;
+		}
+	}
+
+	/// * Save the current packet and write it to the sink, then create a new packet for writing.
+	/// * After the packet is written, the `on_page_written` callback is called with the information of it.
+	/// * The packet is the EOS packet if `is_end_of_stream` is true or it was marked as EOS. After the EOS packet, sealing returns `OggError::WriteAfterEos`.
+	pub fn seal_packet(&mut self, granule_position: u64, is_end_of_stream: bool) -> io::Result<()> {
+		let (bytes, info) = self.pager().seal(granule_position, is_end_of_stream)?;
+		self.writer.write_all(&bytes)?;
+		self.page_written(&info);
+		Ok(())
+	}
+
+	/// * Seal the current packet with the granule position -1, which means no logical packet finishes in it, e.g. a packet which only carries a part of a large setup header.
+	/// * The granule position of the writer isn't changed, the following packets use it as usual.
+	pub fn seal_page_no_completion(&mut self) -> io::Result<()> {
+		let granule_position = self.granule_position;
+		self.seal_packet(u64::MAX, false)?;
+		self.granule_position = granule_position;
+		Ok(())
+	}
+
+	/// * Write a complete logical packet to the current packet, terminated by a segment shorter than 255 bytes, so that the decoder can find where it ends.
+	/// * A packet of a multiple of 255 bytes is terminated by a zero-sized segment. When the current packet is full, it's sealed and the rest goes to the next packet with the continued flag set.
+	/// * A sealed packet which finishes some logical packets has the granule position set by `set_granule_position()`, otherwise it has the granule position -1.
+	///   So call `set_granule_position()` after writing each logical packet with the granule position at the end of it.
+	pub fn write_packet(&mut self, packet: &[u8]) -> io::Result<()> {
+		self.check_not_ended()?;
+		let mut buf = packet;
+		// The first write of a writer resumed by `resume_mid_packet()` finishes the unfinished packet, so the continued flag is kept.
+		let mut is_continued = self.cur_packet.is_continued && self.cur_packet.segment_table.is_empty();
+		loop {
+			let (written, finished) = self.cur_packet.append_limited(buf, is_continued, self.max_page_payload);
+			buf = &buf[written..];
+			is_continued = is_continued || written > 0;
+			if finished {
+				break;
+			}
+			let granule_position = self.granule_position;
+			let finishes_data = self.cur_packet.segment_table.iter().any(|&s|s < 255);
+			self.seal_packet(if finishes_data {granule_position} else {u64::MAX}, false)?;
+			self.granule_position = granule_position;
+		}
+		Ok(())
+	}
+
+	/// * Write a complete audio packet with the number of samples it decodes to, the granule position is accumulated by the samples.
+	/// * The sealed packets have the granule position at the end of their last finished audio packet, which is more precise than the `on_seal` callback that only sees the byte counts.
+	/// * If the granule position would overflow, an error is returned before the packet is written, see `checked_add_granule()`.
+	pub fn write_audio_packet(&mut self, packet: &[u8], samples: u32) -> io::Result<()> {
+		let granule_position = self.granule_position;
+		self.checked_add_granule(samples as u64)?;
+		let end_position = mem::replace(&mut self.granule_position, granule_position);
+		self.write_packet(packet)?;
+		self.granule_position = end_position;
+		Ok(())
+	}
+
+	/// * Add the samples to the granule position, returns an error instead of wrapping around if it overflows.
+	/// * A wrapped granule position goes backwards, the file becomes unseekable. The granule position -1 (`u64::MAX`) means no packet finishes, so it's an overflow too.
+	/// * The granule position isn't changed if it fails.
+	pub fn checked_add_granule(&mut self, samples: u64) -> io::Result<()> {
+		match self.granule_position.checked_add(samples) {
+			Some(granule_position) if granule_position != u64::MAX => {
+				self.granule_position = granule_position;
+				Ok(())
+			}
+			_ => Err(io::Error::new(ErrorKind::InvalidInput, format!("The granule position overflows: {} + {samples}", self.granule_position))),
+		}
+	}
+
+	/// * Write a codec header packet (e.g. Vorbis identification/comment/setup, `OpusHead`/`OpusTags`) on its own pages.
+	/// * The buffered data is sealed first, so that header packets and audio data never share a packet.
+	/// * The packets that finish the header have the granule position 0, the packets that only carry a part of it have the granule position -1.
+	/// * The granule position of the writer is kept, so the audio packets written after it continue from where they were.
+	pub fn write_header_packet(&mut self, packet: &[u8]) -> io::Result<()> {
+		self.check_not_ended()?;
+		if !self.cur_packet.segment_table.is_empty() {
+			self.seal_packet(self.granule_position, false)?;
+		}
+		let granule_position = self.granule_position;
+		self.write_packet(packet)?;
+		self.seal_packet(0, false)?;
+		self.granule_position = granule_position;
+		Ok(())
+	}
+
+	/// * Write a pre-built packet directly to the sink, e.g. when remuxing, bypassing the current packet, so that its data isn't re-chunked.
+	/// * The packet is renumbered to the stream ID and the packet index of the writer, and its checksum is recomputed. Its flags, granule position and segment table are kept.
+	///   To keep the original packet indices, call `set_page_sequence()` with the index of the first packet before writing it, or disable the renumbering by `set_renumber_pages()`.
+	/// * The buffered data in the current packet is sealed first, so the packets stay in order. After an EOS packet, nothing more can be written.
+	/// * The packet is checked by `OggPacket::verify()` before it's written, so a corrupt packet built by hand isn't written.
+	pub fn write_page(&mut self, mut page: OggPacket) -> io::Result<()> {
+		self.check_not_ended()?;
+		page.verify()?;
+		if !self.cur_packet.segment_table.is_empty() {
+			self.seal_packet(self.granule_position, false)?;
+		}
+		page.stream_id = self.stream_id;
+		if self.renumber_pages {
+			page.packet_index = self.packet_index;
+		}
+		page.checksum = page.compute_checksum_with(self.capture_pattern);
+		page.write_to_with_checksum(&mut self.writer, page.checksum, self.capture_pattern)?;
+		self.has_sealed = true;
+		self.ended = page.is_last_page();
+		if page.granule_position != u64::MAX {
+			self.granule_position = page.granule_position;
+		}
+		self.set_page_sequence(page.packet_index.wrapping_add(1));
+		// Like sealing, a page ending with a 255 segment leaves its last packet unterminated, the next packet continues it.
+		self.cur_packet.is_continued = match page.segment_table.last() {
+			Some(&last) => last == 255,
+			None => page.is_continued,
+		};
+		self.page_written(&page.as_page_info());
+		Ok(())
+	}
+
+	/// * Write the pre-built packets in order by `write_page()`
+	pub fn write_pages<I>(&mut self, pages: I) -> io::Result<()>
+	where
+		I: IntoIterator<Item = OggPacket> {
+		for page in pages {
+			self.write_page(page)?;
+		}
+		Ok(())
+	}
+
+	/// * Seal whatever is buffered in the current packet as a non-EOS packet and write it to the sink, even if it's short or empty.
+	/// * Then a fresh packet is created for writing, and the underlying writer is flushed.
+	/// * Useful for low-latency streaming. Note that every packet costs at least 27 bytes of header, frequent flushing increases the overhead.
+	pub fn flush_page(&mut self, granule_position: u64) -> io::Result<()> {
+		self.seal_packet(granule_position, false)?;
+		self.writer.flush()
+	}
+}
+
+impl<W> Write for OggStreamWriter<W>
+where
+	W: Write + Debug {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.check_not_ended()?;
+		if self.packet_per_write {
+			self.write_packet(buf)?;
+			return Ok(buf.len());
+		}
+		let mut buf = buf;
+		let mut written_total = 0usize;
+		while !buf.is_empty() {
+			let written = self.pager().fill(buf);
+			if written == 0 {
+				// The full packet is sealed before consuming more data, so a failed callback leaves the rest of the data unconsumed.
+				self.granule_position = match (self.on_seal)(self.cur_packet.get_inner_data_size()) {
+					Ok(granule_position) => granule_position,
+					Err(_) if written_total > 0 => break,
+					Err(e) => return Err(e),
+				};
+				self.seal_packet(self.granule_position, false)?;
+				continue;
+			}
+			buf = &buf[written..];
+			written_total += written;
+		}
+		Ok(written_total)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.writer.flush()
+	}
+}
+
+impl<W> Debug for OggStreamWriter<W>
+where
+	W: Write + Debug {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		f.debug_struct(&format!("OggStreamWriter<{}>", std::any::type_name::<W>()))
+		.field("writer", &self.writer)
+		.field("stream_id", &format_args!("0x{:08x}", self.stream_id))
+		.field("packet_index", &self.packet_index)
+		.field("cur_packet", &self.cur_packet)
+		.field("granule_position", &self.granule_position)
+		.field("on_seal", &format_args!("<closure>"))
+		.field("bytes_written", &self.bytes_written)
+		.field("on_page_written", &format_args!("{}", if self.on_page_written.is_some() {"<closure>"} else {"None"}))
+		.field("max_page_payload", &self.max_page_payload)
+		.finish()
+	}
+}
+
+/// * The builder for `OggStreamWriter`
+pub struct OggStreamWriterBuilder {
+	stream_id: u32,
+	max_page_payload: usize,
+	on_seal: Option<Box<dyn FnMut(usize) -> io::Result<u64>>>,
+}
+
+impl OggStreamWriterBuilder {
+	pub fn new() -> Self {
+		Self {
+			stream_id: 0,
+			max_page_payload: OggPacket::MAX_PAYLOAD,
+			on_seal: None,
+		}
+	}
+
+	/// * Set the stream ID, the default is 0, which is valid but likely to collide with another stream, see `random_stream_id()`
+	pub fn stream_id(mut self, stream_id: u32) -> Self {
+		self.stream_id = stream_id;
+		self
+	}
+
+	/// * Set the max data size of a packet, it's clamped between 255 and 65025 (the default).
+	/// * Smaller packets mean lower latency but more overhead.
+	pub fn max_page_payload(mut self, max_page_payload: usize) -> Self {
+		self.max_page_payload = max_page_payload.clamp(255, OggPacket::MAX_PAYLOAD);
+		self
+	}
+
+	/// * Set the callback for updating the granule position when the `Write` trait seals a packet
+	pub fn on_seal(mut self, mut on_seal: impl FnMut(usize) -> u64 + 'static) -> Self {
+		self.on_seal = Some(Box::new(move |size|Ok(on_seal(size))));
+		self
+	}
+
+	/// * Set the callback which could fail, its error is returned by the `write()` call which seals the packet
+	pub fn try_on_seal(mut self, on_seal: impl FnMut(usize) -> io::Result<u64> + 'static) -> Self {
+		self.on_seal = Some(Box::new(on_seal));
+		self
+	}
+
+	/// * Build the `OggStreamWriter` with the writer
+	pub fn build<W>(self, writer: W) -> OggStreamWriter<W>
+	where
+		W: Write + Debug {
+		let mut ret = OggStreamWriter::new(writer, self.stream_id);
+		ret.max_page_payload = self.max_page_payload;
+		if let Some(on_seal) = self.on_seal {
+			ret.on_seal = on_seal;
+		}
+		ret
+	}
+}
+
+impl Default for OggStreamWriterBuilder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Debug for OggStreamWriterBuilder {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		f.debug_struct("OggStreamWriterBuilder")
+		.field("stream_id", &format_args!("0x{:08x}", self.stream_id))
+		.field("max_page_payload", &self.max_page_payload)
+		.field("on_seal", &format_args!("{}", if self.on_seal.is_some() {"<closure>"} else {"None"}))
+		.finish()
+	}
+}
+
+/// * The clonable settings of an `OggStreamWriter`, without the writer and the `on_seal` callback, for setting up several writers the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriterConfig {
+	/// * The stream ID of the writers
+	pub stream_id: u32,
+
+	/// * The max data size of a packet, it's clamped between 255 and 65025 when applied
+	pub max_page_payload: usize,
+
+	/// * The packet index of the first packet, a non-zero index makes the first packet a non-BOS packet
+	pub start_sequence: u32,
+
+	/// * If each `write()` call is written as a logical packet, see `OggStreamWriter::set_packet_per_write()`
+	pub packet_per_write: bool,
+}
+
+impl WriterConfig {
+	/// * Get the settings of the writer
+	pub fn from_writer<W>(writer: &OggStreamWriter<W>) -> Self
+	where
+		W: Write + Debug {
+		Self {
+			stream_id: writer.stream_id,
+			max_page_payload: writer.max_page_payload,
+			start_sequence: writer.packet_index,
+			packet_per_write: writer.packet_per_write,
+		}
+	}
+
+	/// * Apply the settings to a writer, it fails like `OggStreamWriter::set_stream_id()` if the writer already wrote some packets.
+	pub fn apply<W>(&self, writer: &mut OggStreamWriter<W>) -> io::Result<()>
+	where
+		W: Write + Debug {
+		writer.set_stream_id(self.stream_id)?;
+		writer.set_max_page_payload(self.max_page_payload);
+		writer.set_page_sequence(self.start_sequence);
+		writer.set_packet_per_write(self.packet_per_write);
+		Ok(())
+	}
+}
+
+impl Default for WriterConfig {
+	fn default() -> Self {
+		Self {
+			stream_id: 0,
+			max_page_payload: OggPacket::MAX_PAYLOAD,
+			start_sequence: 0,
+			packet_per_write: false,
+		}
+	}
+}
+
+impl<W> Drop for OggStreamWriter<W>
+where
+	W: Write + Debug {
+	fn drop(&mut self) {
+		if self.flush_on_drop && !self.ended {
+			self.seal_packet(self.granule_position, true).unwrap();
+		}
+	}
+}
+
+#[test]
+fn test_ogg() {
+	use std::{
+		fs::File,
+		io::BufReader,
+	};
+	let mut oggreader = OggStreamReader::new(BufReader::new(File::open("test.ogg").unwrap()));
+	loop {
+		let packet = oggreader.get_packet().unwrap();
+		if let Some(packet) = packet {
+			dbg!(packet);
+		} else {
+			break;
+		}
+	}
+}
+
+#[test]
+fn test_from_cursor() {
+	let mut stream = Vec::<u8>::new();
+	for i in 0..3 {
+		let mut packet = OggPacket::new(0x12345678, OggPacketType::Continuation, i);
+		packet.write(&[i as u8; 300]);
+		stream.extend(packet.into_bytes());
+	}
+
+	// Clean end
+	let mut cursor = Cursor::new(stream.clone());
+	let (packets, error) = OggPacket::from_cursor(&mut cursor);
+	assert_eq!(packets.len(), 3);
+	assert_eq!(error, None);
+	assert_eq!(cursor.position(), stream.len() as u64);
+
+	// Truncated tail
+	let mut truncated = stream.clone();
+	truncated.truncate(stream.len() - 10);
+	let mut cursor = Cursor::new(truncated);
+	let (packets, error) = OggPacket::from_cursor(&mut cursor);
+	assert_eq!(packets.len(), 2);
+	assert!(matches!(error, Some(OggError::UnexpectedEof{..})));
+	assert_eq!(cursor.position(), (stream.len() / 3 * 2) as u64);
+
+	// Garbage tail
+	let mut garbage = stream.clone();
+	garbage.extend(b"This is not an Ogg packet at all");
+	let mut cursor = Cursor::new(garbage);
+	let (packets, error) = OggPacket::from_cursor(&mut cursor);
+	assert_eq!(packets.len(), 3);
+	assert_eq!(error, Some(OggError::BadCapturePattern(*b"This")));
+	assert_eq!(cursor.position(), stream.len() as u64);
+}
+
+#[test]
+fn test_flush_page() {
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		writer.write_all(b"Hello").unwrap();
+		writer.flush_page(5).unwrap();
+		writer.write_all(b"World").unwrap();
+		writer.flush_page(10).unwrap();
+		writer.flush_page(10).unwrap();
+	}
+	let (packets, error) = OggPacket::from_cursor(&mut Cursor::new(sink));
+	assert_eq!(error, None);
+	assert_eq!(packets.len(), 4);
+	assert_eq!(packets[0].packet_type, OggPacketType::BeginOfStream);
+	assert_eq!(packets[0].get_inner_data(), b"Hello");
+	assert_eq!(packets[0].granule_position, 5);
+	assert_eq!(packets[1].packet_type, OggPacketType::Continuation);
+	assert_eq!(packets[1].get_inner_data(), b"World");
+	assert!(packets[2].get_inner_data().is_empty());
+	assert_eq!(packets[3].packet_type, OggPacketType::EndOfStream);
+}
+
+#[test]
+fn test_append_stream() {
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		writer.write_all(&[1u8; 100000]).unwrap();
+	}
+	let mut reader = OggStreamReader::new(Cursor::new(sink.clone()));
+	while reader.get_packet().unwrap().is_some() {}
+	let last_index = reader.get_last_packet_index().unwrap();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		writer.set_page_sequence(last_index + 1);
+		writer.write_all(&[2u8; 100000]).unwrap();
+	}
+	let (packets, error) = OggPacket::from_cursor(&mut Cursor::new(sink));
+	assert_eq!(error, None);
+	assert!(packets.len() > (last_index + 1) as usize);
+	for (i, packet) in packets.iter().enumerate() {
+		assert_eq!(packet.packet_index, i as u32);
+	}
+	assert_eq!(packets[last_index as usize + 1].packet_type, OggPacketType::Continuation);
+}
+
+#[test]
+fn test_detect_gaps() {
+	let mut stream = Vec::<u8>::new();
+	for i in 0..4 {
+		if i == 2 {
+			continue;
+		}
+		let packet_type = if i == 0 {OggPacketType::BeginOfStream} else {OggPacketType::Continuation};
+		let mut packet = OggPacket::new(0x12345678, packet_type, i);
+		packet.write(&[i as u8; 100]);
+		stream.extend(packet.into_bytes());
+	}
+	let mut reader = OggStreamReader::new(Cursor::new(stream));
+	reader.set_detect_gaps(true);
+	assert_eq!(reader.get_packet().unwrap().unwrap().packet_index, 0);
+	assert_eq!(reader.get_packet().unwrap().unwrap().packet_index, 1);
+	let error = reader.get_packet().unwrap_err();
+	let error = error.get_ref().unwrap().downcast_ref::<OggError>().unwrap();
+	assert_eq!(*error, OggError::SequenceGap{stream_id: 0x12345678, expected: 2, got: 3});
+	assert_eq!(reader.get_packet().unwrap().unwrap().packet_index, 3);
+	assert!(reader.get_packet().unwrap().is_none());
+}
+
+#[test]
+fn test_packet_offset() {
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		for i in 0..10 {
+			writer.write_all(&vec![i as u8; 1000 * i]).unwrap();
+			writer.flush_page(i as u64).unwrap();
+		}
+	}
+	let total_length = sink.len() as u64;
+	let (packets, _) = OggPacket::from_cursor(&mut Cursor::new(sink.clone()));
+	let mut reader = OggStreamReader::new(Cursor::new(sink));
+	let mut expected_offset = 0u64;
+	for packet in packets {
+		let (offset, read) = reader.get_packet_with_offset().unwrap().unwrap();
+		assert_eq!(offset, expected_offset);
+		assert_eq!(read.packet_index, packet.packet_index);
+		expected_offset += packet.into_bytes().len() as u64;
+		assert_eq!(reader.stream_position(), expected_offset);
+	}
+	assert!(reader.get_packet_with_offset().unwrap().is_none());
+	assert_eq!(reader.stream_position(), total_length);
+}
+
+#[test]
+fn test_chain_reader() {
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let mut chained = test_ogg.clone();
+	{
+		let mut writer = OggStreamWriter::new(&mut chained, 0x12345678);
+		writer.write_all(b"OpusHead\x01\x02\x38\x01\x80\xbb\0\0\0\0\0").unwrap();
+		writer.flush_page(0).unwrap();
+		writer.write_all(b"Some audio data").unwrap();
+	}
+	let mut reader = OggChainReader::new(Cursor::new(chained));
+	let first = reader.next_chain().unwrap().unwrap();
+	assert_eq!(first.codec, Codec::Vorbis{sample_rate: 44100});
+	let mut num_packets = 0;
+	while let Some(packet) = reader.get_packet().unwrap() {
+		assert_eq!(packet.stream_id, first.stream_id);
+		num_packets += 1;
+	}
+	assert_eq!(num_packets, OggPacket::from_cursor(&mut Cursor::new(test_ogg)).0.len());
+	let second = reader.next_chain().unwrap().unwrap();
+	assert_eq!(second, ChainInfo{stream_id: 0x12345678, codec: Codec::Opus{pre_skip: 312}});
+	let packet = reader.get_packet().unwrap().unwrap();
+	assert_eq!(packet.packet_type, OggPacketType::BeginOfStream);
+	assert!(reader.next_chain().unwrap().is_none());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() {
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let (packets, _) = OggPacket::from_cursor(&mut Cursor::new(test_ogg.clone()));
+	let json = serde_json::to_string(&packets).unwrap();
+	let packets: Vec<OggPacket> = serde_json::from_str(&json).unwrap();
+	let mut bytes = Vec::<u8>::new();
+	for mut packet in packets {
+		let checksum = packet.checksum;
+		packet.recompute_checksum();
+		assert_eq!(packet.checksum, checksum);
+		bytes.extend(packet.into_bytes());
+	}
+	assert_eq!(bytes, test_ogg);
+}
+
+#[test]
+fn test_try_from_and_display() {
+	let mut packet = OggPacket::new(0x12345678, OggPacketType::Continuation, 3);
+	packet.granule_position = 44100;
+	packet.write(&[0u8; 1200]);
+	let mut bytes = packet.into_bytes();
+	let parsed = OggPacket::try_from(&bytes[..]).unwrap();
+	assert_eq!(parsed.to_string(), "OggPage[stream=0x12345678 seq=3 flags=CONT granule=44100 segs=5 bytes=1200]");
+	bytes.extend(b"trailing bytes");
+	assert_eq!(OggPacket::try_from(&bytes[..]).unwrap().data, parsed.data);
+	assert!(matches!(OggPacket::try_from(&bytes[..100]), Err(OggError::UnexpectedEof{got: 100, needed: 1232})));
+
+	// The owned bytes must be exactly one packet
+	let packet_bytes = Vec::<u8>::from(parsed.clone());
+	assert_eq!(packet_bytes, bytes[..1232]);
+	assert_eq!(OggPacket::try_from(packet_bytes.clone()).unwrap(), parsed);
+	assert_eq!(OggPacket::try_from(bytes.clone()), Err(OggError::TrailingBytes{packet_size: 1232, got: bytes.len()}));
+	assert!(matches!(OggPacket::try_from(packet_bytes[..100].to_vec()), Err(OggError::UnexpectedEof{got: 100, needed: 1232})));
+}
+
+#[test]
+fn test_validate() {
+	let mut packet = OggPacket::new(0, OggPacketType::BeginOfStream, 0);
+	packet.write(&[0u8; 1000]);
+	assert_eq!(packet.validate(), Ok(()));
+	packet.data.push(0);
+	assert_eq!(packet.validate(), Err(OggError::DataSizeMismatch{expected: 1000, got: 1001}));
+	packet.clear();
+	packet.segment_table = vec![0; 256];
+	assert_eq!(packet.clone().try_into_bytes(), Err(OggError::TooManySegments(256)));
+	packet.segment_table.pop();
+	assert!(packet.clone().try_into_bytes().is_ok());
+	packet.version = 1;
+	assert_eq!(packet.verify(), Err(OggError::BadVersion(1)));
+	assert_eq!(packet.validate(), packet.verify());
+
+	// The writer doesn't write a corrupt packet
+	let mut sink = Vec::<u8>::new();
+	let mut writer = OggStreamWriter::new(&mut sink, 0);
+	let e = writer.write_page(packet).unwrap_err();
+	assert_eq!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(&OggError::BadVersion(1)));
+	assert_eq!(writer.packet_index, 0);
+}
+
+#[test]
+fn test_write_header_packet() {
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		writer.write_header_packet(b"OpusHead\x01\x02\x38\x01\x80\xbb\0\0\0\0\0").unwrap();
+		writer.write_header_packet(&[0u8; 255 * 300]).unwrap();
+		writer.write_all(b"Audio").unwrap();
+		writer.seal_packet(960, false).unwrap();
+	}
+	let (packets, error) = OggPacket::from_cursor(&mut Cursor::new(sink));
+	assert_eq!(error, None);
+	assert_eq!(packets.len(), 5);
+	assert_eq!(packets[0].packet_type, OggPacketType::BeginOfStream);
+	assert_eq!(packets[0].granule_position, 0);
+	assert_eq!(packets[0].segment_table.len(), 1);
+	assert_eq!(packets[1].granule_position, u64::MAX);
+	assert_eq!(packets[1].segment_table, vec![255u8; 255]);
+	assert_eq!(packets[2].granule_position, 0);
+	assert_eq!(*packets[2].segment_table.last().unwrap(), 0);
+	assert!(packets[2].is_continued);
+	assert_eq!(packets[1].data.len() + packets[2].data.len(), 255 * 300);
+	assert_eq!(packets[3].get_inner_data(), b"Audio");
+	assert_eq!(packets[3].granule_position, 960);
+
+	// A header packet after the audio doesn't reset the granule position
+	let mut writer = OggStreamWriter::new(Vec::<u8>::new(), 0x12345678);
+	writer.write_audio_packet(b"Audio", 960).unwrap();
+	writer.write_header_packet(b"header").unwrap();
+	assert_eq!(writer.granule_position, 960);
+	writer.write_audio_packet(b"Audio", 960).unwrap();
+	writer.seal_packet(writer.granule_position, true).unwrap();
+	let (packets, _) = OggPacket::parse_all(&writer.writer);
+	assert_eq!(packets.iter().map(|p|p.granule_position).collect::<Vec<u64>>(), vec![960, 0, 1920]);
+}
+
+#[test]
+fn test_writer_builder() {
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriterBuilder::new()
+			.stream_id(0x12345678)
+			.max_page_payload(4096)
+			.on_seal(|size|size as u64 * 2)
+			.build(&mut sink);
+		assert_eq!(writer.get_max_page_payload(), 4096);
+		writer.write_all(&[0u8; 10000]).unwrap();
+	}
+	let (packets, error) = OggPacket::from_cursor(&mut Cursor::new(sink));
+	assert_eq!(error, None);
+	assert_eq!(packets.len(), 3);
+	for packet in packets.iter() {
+		assert_eq!(packet.stream_id, 0x12345678);
+		assert!(packet.data.len() <= 4096);
+	}
+	assert_eq!(packets[0].granule_position, packets[0].data.len() as u64 * 2);
+}
+
+#[test]
+fn test_peek_packet() {
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let mut reader = OggStreamReader::new(Cursor::new(test_ogg));
+	let peeked = reader.peek_packet().unwrap().unwrap().clone();
+	assert_eq!(peeked.packet_type, OggPacketType::BeginOfStream);
+	assert_eq!(reader.peek_packet().unwrap().unwrap().data, peeked.data);
+	assert_eq!(reader.stream_position(), 0);
+	let (offset, packet) = reader.get_packet_with_offset().unwrap().unwrap();
+	assert_eq!(offset, 0);
+	assert_eq!(packet.data, peeked.data);
+	let next = reader.peek_packet().unwrap().unwrap().clone();
+	assert_eq!(next.packet_index, 1);
+	assert_eq!(reader.get_packet().unwrap().unwrap().data, next.data);
+}
+
+#[test]
+fn test_strict() {
+	let mut stream = Vec::<u8>::new();
+	for (i, packet_index) in [0u32, 1, 1, 2].into_iter().enumerate() {
+		let packet_type = match i {
+			0 => OggPacketType::BeginOfStream,
+			3 => OggPacketType::EndOfStream,
+			_ => OggPacketType::Continuation,
+		};
+		let mut packet = OggPacket::new(0x12345678, packet_type, packet_index);
+		packet.write(&vec![0u8; if i == 3 {255} else {100}]);
+		stream.extend(packet.into_bytes());
+	}
+
+	let mut reader = OggStreamReader::new(Cursor::new(stream.clone()));
+	let mut num_packets = 0;
+	while reader.get_packet().unwrap().is_some() {
+		num_packets += 1;
+	}
+	assert_eq!(num_packets, 4);
+
+	let mut reader = OggStreamReader::new(Cursor::new(stream));
+	reader.set_strict(true);
+	let mut errors = Vec::<OggError>::new();
+	let mut num_packets = 0;
+	loop {
+		match reader.get_packet() {
+			Ok(Some(_)) => num_packets += 1,
+			Ok(None) => break,
+			Err(e) => errors.push(e.into_inner().unwrap().downcast::<OggError>().map(|e|*e).unwrap()),
+		}
+	}
+	assert_eq!(num_packets, 4);
+	assert_eq!(errors, vec![
+		OggError::SequenceRegression{stream_id: 0x12345678, prev: 1, got: 1},
+		OggError::UnterminatedPacket{stream_id: 0x12345678, packet_index: 2},
+	]);
+}
+
+#[test]
+fn test_packet_builder() {
+	let mut packet = OggPacket::new(0x12345678, OggPacketType::Continuation, 1);
+	assert_eq!(packet.append_page_data(&[1u8; 300], true), 300);
+	assert!(packet.is_continued);
+	assert_eq!(packet.segment_table, vec![255, 45]);
+	assert_eq!(packet.append_page_data(&[2u8; 255 * 253], false), 255 * 252);
+	assert_eq!(packet.segment_table.len(), 254);
+	assert_eq!(packet.append_page_data(&[3u8; 1000], false), 255);
+	assert!(packet.is_continued);
+	assert_eq!(packet.segment_table.len(), 255);
+	assert_eq!(*packet.segment_table.last().unwrap(), 255);
+
+	let mut builder = PacketBuilder::new(0x12345678, 0);
+	let logical_packet: Vec<u8> = (0..255 * 255).map(|i|i as u8).collect();
+	let packets = builder.build(&logical_packet, 1000, true);
+	assert_eq!(packets.len(), 2);
+	assert_eq!(builder.packet_index, 2);
+	assert_eq!(packets[0].packet_type, OggPacketType::BeginOfStream);
+	assert!(!packets[0].is_continued);
+	assert_eq!(packets[0].granule_position, u64::MAX);
+	assert_eq!(packets[0].segment_table, vec![255u8; 254]);
+	assert_eq!(packets[1].packet_type, OggPacketType::EndOfStream);
+	assert!(packets[1].is_continued);
+	assert_eq!(packets[1].granule_position, 1000);
+	assert_eq!(packets[1].segment_table, vec![255, 0]);
+	let bytes: Vec<u8> = packets.into_iter().flat_map(|p|p.into_bytes()).collect();
+	assert_eq!(bytes[5], 2);
+	let (parsed, error) = OggPacket::from_cursor(&mut Cursor::new(bytes));
+	assert_eq!(error, None);
+	assert!(parsed[1].is_continued);
+	assert_eq!(parsed[1].to_string(), "OggPage[stream=0x12345678 seq=1 flags=EOS|CONTINUED granule=1000 segs=2 bytes=255]");
+	assert_eq!([parsed[0].get_inner_data(), parsed[1].get_inner_data()].concat(), logical_packet);
+}
+
+#[test]
+fn test_header_bytes() {
+	let mut packet = OggPacket::new(0x12345678, OggPacketType::BeginOfStream, 0);
+	packet.write(b"OpusHead");
+	let mut bytes = packet.into_bytes();
+	let parsed = OggPacket::try_from(&bytes[..]).unwrap();
+	let (header, payload) = OggPacket::from_bytes_ref(&bytes).unwrap();
+	assert_eq!(header, &parsed.header_bytes()[..]);
+	assert_eq!(header.len(), 28);
+	assert_eq!(payload, b"OpusHead");
+	// A corrupt payload is still split, and the difference is found with the re-serialized packet.
+	bytes[30] = b'X';
+	let (header, payload) = OggPacket::from_bytes_ref(&bytes).unwrap();
+	assert_eq!(header, &parsed.header_bytes()[..]);
+	assert_ne!(payload, &parsed.get_inner_data()[..]);
+	assert!(OggPacket::from_bytes_ref(&bytes[..30]).is_err());
+}
+
+#[test]
+fn test_parse_all() {
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let (packets, consumed) = OggPacket::parse_all(&test_ogg);
+	assert_eq!(packets.len(), 5);
+	assert_eq!(consumed, test_ogg.len());
+	let (packets, consumed) = OggPacket::parse_all(&test_ogg[..test_ogg.len() - 1]);
+	assert_eq!(packets.len(), 4);
+	assert_eq!(consumed, packets.into_iter().map(|p|p.into_bytes().len()).sum::<usize>());
+	assert_eq!(OggPacket::parse_all(&[]).1, 0);
+}
+
+#[test]
+fn test_repair_checksums() {
+	let original = std::fs::read("test.ogg").unwrap();
+	let mut test_ogg = original.clone();
+	let second = OggPacket::get_length(&test_ogg).unwrap();
+	test_ogg[22] ^= 0xFF;
+	test_ogg[second + 22] ^= 0xFF;
+	assert!(matches!(OggPacket::parse_all(&test_ogg), (packets, 0) if packets.is_empty()));
+	let truncated = test_ogg.len() - 10;
+	assert_eq!(OggPacket::repair_checksums(&mut test_ogg[..truncated]).unwrap(), 2);
+	assert_eq!(test_ogg, original);
+	assert_eq!(OggPacket::repair_checksums(&mut test_ogg).unwrap(), 0);
+}
+
+#[test]
+fn test_reader_into_inner() {
+	let mut data = std::fs::read("test.ogg").unwrap();
+	data.extend(b"trailing data");
+	let mut oggreader = OggStreamReader::new(Cursor::new(data.clone()));
+	while oggreader.get_packet().unwrap().is_some() {}
+	let (cursor, cached_bytes) = oggreader.into_inner();
+	assert_eq!(cached_bytes, b"trailing data");
+	assert_eq!(cursor.position() as usize, data.len());
+
+	let mut oggreader = OggStreamReader::new(Cursor::new(data.clone()));
+	oggreader.get_packet().unwrap();
+	oggreader.peek_packet().unwrap();
+	let offset = oggreader.stream_position() as usize;
+	let (mut cursor, mut rest) = oggreader.into_inner();
+	cursor.read_to_end(&mut rest).unwrap();
+	assert_eq!(rest, &data[offset..]);
+
+	// The peeked packet is given back as it was read, with its capture pattern.
+	let mut data = Vec::new();
+	for i in 0..2 {
+		let mut packet = OggPacket::new(0x12345678, OggPacketType::Continuation, i);
+		packet.write(b"Hello");
+		data.extend(packet.to_bytes_with_capture_pattern(*b"Test"));
+	}
+	let mut oggreader = OggStreamReader::new(Cursor::new(data.clone()));
+	oggreader.set_capture_pattern(*b"Test");
+	oggreader.get_packet().unwrap();
+	oggreader.peek_packet().unwrap();
+	let offset = oggreader.stream_position() as usize;
+	let (mut cursor, mut rest) = oggreader.into_inner();
+	cursor.read_to_end(&mut rest).unwrap();
+	assert_eq!(rest, &data[offset..]);
+	assert_eq!(&rest[..4], b"Test");
+}
+
+#[test]
+fn test_reset_stream_state() {
+	fn single_stream(stream_id: u32) -> Vec<u8> {
+		let mut bytes = Vec::<u8>::new();
+		for (i, packet_type) in [OggPacketType::BeginOfStream, OggPacketType::EndOfStream].into_iter().enumerate() {
+			let mut packet = OggPacket::new(stream_id, packet_type, i as u32);
+			packet.write(b"Hello");
+			bytes.extend(packet.into_bytes());
+		}
+		bytes
+	}
+	let mut oggreader = OggStreamReader::new(Cursor::new(single_stream(1)));
+	assert_eq!(oggreader.get_packet().unwrap().unwrap().stream_id, 1);
+	assert_eq!(oggreader.stream_id, 1);
+	assert!(oggreader.get_packet().unwrap().is_some());
+	assert!(oggreader.get_packet().unwrap().is_none());
+	assert!(oggreader.is_eos());
+
+	// The next chained stream arrives in the same reader.
+	oggreader.reader.get_mut().extend(single_stream(2));
+	assert!(oggreader.get_packet().unwrap().is_none());
+	oggreader.reset_stream_state();
+	assert!(!oggreader.is_eos() && !oggreader.is_eof());
+	assert_eq!(oggreader.get_packet().unwrap().unwrap().packet_type, OggPacketType::BeginOfStream);
+	assert_eq!(oggreader.stream_id, 2);
+	assert!(oggreader.get_packet().unwrap().is_some());
+	assert!(oggreader.get_packet().unwrap().is_none());
+}
+
+#[test]
+fn test_page_classification() {
+	let (packets, _) = OggPacket::parse_all(&std::fs::read("test.ogg").unwrap());
+	assert!(packets[0].is_first_page() && !packets[0].is_last_page());
+	assert!(packets[4].is_last_page() && !packets[4].is_first_page());
+	assert!(packets.iter().filter(|p|p.is_first_page()).count() == 1);
+	let mut packet = OggPacket::new(0, OggPacketType::Continuation, 1);
+	assert!(packet.is_fresh_packet());
+	packet.append_page_data(b"rest", true);
+	assert!(!packet.is_fresh_packet());
+}
+
+#[test]
+fn test_max_page_payload() {
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		writer.set_max_page_payload(1000);
+		writer.write_header_packet(&[1u8; 255 * 8]).unwrap();
+		writer.write_all(&[2u8; 3000]).unwrap();
+		writer.write_all(&[3u8; 100]).unwrap();
+	}
+	let (packets, error) = OggPacket::from_cursor(&mut Cursor::new(sink));
+	assert_eq!(error, None);
+	for packet in packets.iter() {
+		assert!(packet.data.len() <= 1000);
+	}
+	// The header packet of 8 segments spans 3 packets, then it's terminated by a zero-sized segment.
+	assert_eq!(packets[2].segment_table, vec![255, 255, 0]);
+	assert_eq!(packets[2].granule_position, 0);
+	assert!(packets[2].is_continued);
+	let total: usize = packets.iter().map(|p|p.data.len()).sum();
+	assert_eq!(total, 255 * 8 + 3100);
+
+	// A size below a segment is clamped, so each packet still takes some data
+	let mut writer = OggStreamWriter::new(Vec::<u8>::new(), 0x12345678);
+	writer.set_max_page_payload(0);
+	assert_eq!(writer.get_max_page_payload(), 255);
+	assert_eq!(writer.write(&[0u8; 1000]).unwrap(), 1000);
+	writer.discard();
+}
+
+#[test]
+fn test_write_continued() {
+	// The data of the `Write` calls spanning several packets is a single logical packet, so the packets after a full one have the continued flag.
+	for (max_page_payload, size) in [(510usize, 1000usize), (OggPacket::MAX_PAYLOAD, 200000)] {
+		let data: Vec<u8> = (0..size).map(|i|i as u8).collect();
+		let mut sink = Vec::<u8>::new();
+		{
+			let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+			writer.set_max_page_payload(max_page_payload);
+			writer.write_all(&data).unwrap();
+		}
+		let (packets, _) = OggPacket::parse_all(&sink);
+		assert!(packets.len() > 1);
+		assert!(packets[1..].iter().all(|p|p.is_continued));
+		let mut oggreader = OggStreamReader::new(Cursor::new(sink));
+		assert_eq!(oggreader.get_logical_packet().unwrap().unwrap().data, data);
+		assert!(oggreader.get_logical_packet().unwrap().is_none());
+	}
+}
+
+#[test]
+fn test_detect_granule_regression() {
+	let mut bytes = Vec::<u8>::new();
+	for (i, granule_position) in [0u64, 1000, u64::MAX, 900, 2000, 0, 500].into_iter().enumerate() {
+		// The packet 5 begins a chained stream of the same `stream_id`
+		let packet_type = if i == 0 || i == 5 {OggPacketType::BeginOfStream} else {OggPacketType::Continuation};
+		let mut packet = OggPacket::new(0x12345678, packet_type, i as u32);
+		packet.granule_position = granule_position;
+		packet.write(b"data");
+		bytes.extend(packet.into_bytes());
+	}
+	let mut oggreader = OggStreamReader::new(Cursor::new(bytes));
+	oggreader.set_detect_granule_regression(true);
+	let mut granules = Vec::<u64>::new();
+	let mut errors = Vec::<OggError>::new();
+	loop {
+		match oggreader.get_packet() {
+			Ok(Some(packet)) => granules.push(packet.granule_position),
+			Ok(None) => break,
+			Err(e) => errors.push(e.get_ref().unwrap().downcast_ref::<OggError>().unwrap().clone()),
+		}
+	}
+	assert_eq!(granules.len(), 7);
+	assert_eq!(errors, vec![OggError::GranuleRegression{stream_id: 0x12345678, prev: 1000, got: 900}]);
+
+	// A packet with a gap and a regression gets both errors
+	let mut bytes = Vec::<u8>::new();
+	for (packet_index, granule_position) in [(0u32, 1000u64), (2, 500), (3, 600)] {
+		let packet_type = if packet_index == 0 {OggPacketType::BeginOfStream} else {OggPacketType::Continuation};
+		let mut packet = OggPacket::new(0x12345678, packet_type, packet_index);
+		packet.granule_position = granule_position;
+		packet.write(b"data");
+		bytes.extend(packet.into_bytes());
+	}
+	let mut oggreader = OggStreamReader::new(Cursor::new(bytes));
+	oggreader.set_detect_gaps(true);
+	oggreader.set_detect_granule_regression(true);
+	let mut num_packets = 0;
+	let mut errors = Vec::<OggError>::new();
+	loop {
+		match oggreader.get_packet() {
+			Ok(Some(_)) => num_packets += 1,
+			Ok(None) => break,
+			Err(e) => errors.push(e.get_ref().unwrap().downcast_ref::<OggError>().unwrap().clone()),
+		}
+	}
+	assert_eq!(num_packets, 3);
+	assert_eq!(errors, vec![
+		OggError::SequenceGap{stream_id: 0x12345678, expected: 1, got: 2},
+		OggError::GranuleRegression{stream_id: 0x12345678, prev: 1000, got: 500},
+	]);
+}
+
+#[test]
+fn test_streaming_checksum() {
+	let mut packet = OggPacket::new(0x12345678, OggPacketType::Continuation, 1);
+	packet.write(&(0..OggPacket::MAX_PAYLOAD).map(|i|(i * 7) as u8).collect::<Vec<u8>>());
+	let bytes = packet.into_bytes();
+	let mut field_cleared = bytes.clone();
+	field_cleared[22..26].copy_from_slice(&[0u8; 4]);
+	assert_eq!(OggPacket::get_checksum(&bytes).unwrap(), OggPacket::crc(0, &field_cleared));
+	assert_eq!(OggPacket::get_checksum(&bytes).unwrap().to_le_bytes(), bytes[22..26]);
+}
+
+#[test]
+fn test_write_packet() {
+	let mut sink = Vec::<u8>::new();
+	let logical_packets: Vec<Vec<u8>> = [100usize, 255, 70000, 0, 30].iter().enumerate().map(|(i, &size)|vec![i as u8; size]).collect();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		for (i, packet) in logical_packets.iter().enumerate() {
+			writer.write_packet(packet).unwrap();
+			writer.set_granule_position((i as u64 + 1) * 960);
+		}
+		writer.seal_packet(writer.get_granule_position(), true).unwrap();
+	}
+	let (packets, error) = OggPacket::from_cursor(&mut Cursor::new(sink));
+	assert_eq!(error, None);
+	// Dropping the writer doesn't write another packet after the EOS packet.
+	assert_eq!(packets.len(), 2);
+	assert!(!packets[0].is_continued);
+	assert_eq!(&packets[0].segment_table[..3], &[100, 255, 0]);
+	// The first packet finishes the first two logical packets
+	assert_eq!(packets[0].granule_position, 2 * 960);
+	assert!(packets[1].is_continued);
+	assert_eq!(packets[1].granule_position, 5 * 960);
+	assert!(packets[1].is_last_page());
+
+	// Reassemble the logical packets from the lacing values
+	let mut reassembled = Vec::<Vec<u8>>::new();
+	let mut cur = Vec::<u8>::new();
+	for packet in packets.iter() {
+		let mut pos = 0usize;
+		for &s in packet.segment_table.iter() {
+			cur.extend(&packet.data[pos..pos + s as usize]);
+			pos += s as usize;
+			if s < 255 {
+				reassembled.push(mem::take(&mut cur));
+			}
+		}
+	}
+	assert_eq!(reassembled, logical_packets);
+}
+
+#[test]
+fn test_read_size() {
+	#[derive(Debug)]
+	struct CountingReader(Cursor<Vec<u8>>, usize);
+	impl Read for CountingReader {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			self.1 += 1;
+			self.0.read(buf)
+		}
+	}
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let mut counts = Vec::<usize>::new();
+	for read_size in [0usize, 2048, 1 << 20] {
+		let mut oggreader = OggStreamReader::new(CountingReader(Cursor::new(test_ogg.clone()), 0));
+		oggreader.set_read_size(read_size);
+		let mut num_packets = 0usize;
+		while oggreader.get_packet().unwrap().is_some() {
+			num_packets += 1;
+		}
+		assert_eq!(num_packets, 5);
+		counts.push(oggreader.reader.1);
+	}
+	assert!(counts[0] > counts[1] && counts[1] > counts[2]);
+}
+
+#[test]
+fn test_from_bytes_lenient() {
+	let mut test_ogg = std::fs::read("test.ogg").unwrap();
+	let mut packet_length = 0usize;
+	let (packet, is_valid) = OggPacket::from_bytes_lenient(&test_ogg, &mut packet_length).unwrap();
+	assert!(is_valid);
+	let expected = packet.checksum;
+	test_ogg[22] ^= 0xFF;
+	let err = OggPacket::from_bytes(&test_ogg, &mut packet_length).unwrap_err();
+	let got = expected ^ 0xFF;
+	assert_eq!(err.get_ref().unwrap().downcast_ref::<OggError>(), Some(&OggError::ChecksumMismatch{expected, got, offset: 0}));
+	let (lenient, is_valid) = OggPacket::from_bytes_lenient(&test_ogg, &mut packet_length).unwrap();
+	assert!(!is_valid);
+	assert_eq!(lenient.checksum, got);
+	assert_eq!(lenient.data, packet.data);
+}
+
+#[test]
+fn test_mux_pages() {
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let (mut packets, _) = OggPacket::parse_all(&test_ogg);
+	// The checksums are recalculated
+	packets[1].checksum = 0;
+	assert_eq!(mux_pages(packets.clone()), test_ogg);
+	assert_eq!(try_mux_pages(packets.clone()), Ok(test_ogg));
+	packets.swap(2, 3);
+	assert_eq!(try_mux_pages(packets), Err(OggError::SequenceRegression{stream_id: 1201010866, prev: 3, got: 2}));
+}
+
+#[test]
+fn test_max_buffer_bytes() {
+	let mut packet = OggPacket::new(0x12345678, OggPacketType::BeginOfStream, 0);
+	packet.write(&[0u8; 10000]);
+	let bytes = packet.into_bytes();
+	let mut oggreader = OggStreamReader::new(Cursor::new(bytes.clone()));
+	oggreader.set_max_buffer_bytes(4096);
+	let err = oggreader.get_packet().unwrap_err();
+	assert_eq!(err.get_ref().unwrap().downcast_ref::<OggError>(), Some(&OggError::BufferOverflow{limit: 4096, needed: bytes.len()}));
+	let mut oggreader = OggStreamReader::new(Cursor::new(bytes));
+	oggreader.set_read_size(1 << 20);
+	assert!(oggreader.get_packet().unwrap().is_some());
+}
+
+#[test]
+fn test_page_size() {
+	let mut packet = OggPacket::new(0x12345678, OggPacketType::BeginOfStream, 0);
+	assert_eq!(packet.page_overhead(), 27);
+	packet.write(&[0u8; 1000]);
+	assert_eq!(packet.page_overhead(), 27 + 4);
+	assert_eq!(packet.total_page_size(), 27 + 4 + 1000);
+	let bytes = packet.clone().into_bytes();
+	assert_eq!(packet.total_page_size(), bytes.len());
+	assert_eq!(OggPacket::get_length(&bytes).unwrap(), bytes.len());
+}
+
+#[test]
+fn test_set_stream_id() {
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0);
+		writer.write_all(b"metadata").unwrap();
+		writer.set_stream_id(0x12345678).unwrap();
+		writer.flush_page(0).unwrap();
+		assert!(writer.set_stream_id(0x87654321).is_err());
+	}
+	let (packets, error) = OggPacket::from_cursor(&mut Cursor::new(sink));
+	assert_eq!(error, None);
+	assert!(packets.iter().all(|p|p.stream_id == 0x12345678));
+	assert_eq!(packets[0].get_inner_data(), b"metadata");
+}
+
+#[test]
+fn test_truncated() {
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let mut oggreader = OggStreamReader::new(Cursor::new(test_ogg.clone()));
+	oggreader.set_read_size(100);
+	for _ in 0..5 {
+		assert!(oggreader.get_packet().unwrap().is_some());
+	}
+	assert!(oggreader.get_packet().unwrap().is_none());
+	assert!(oggreader.is_eof());
+
+	let mut oggreader = OggStreamReader::new(Cursor::new(test_ogg[..test_ogg.len() - 1].to_vec()));
+	oggreader.set_read_size(100);
+	for _ in 0..4 {
+		assert!(oggreader.get_packet().unwrap().is_some());
+	}
+	let err = oggreader.get_packet().unwrap_err();
+	assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+	let last_length = OggPacket::parse_all(&test_ogg).0.pop().unwrap().total_page_size();
+	assert_eq!(err.get_ref().unwrap().downcast_ref::<OggError>(), Some(&OggError::Truncated{got: last_length - 1, needed: last_length}));
+}
+
+#[test]
+fn test_write_audio_packet() {
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		writer.write_header_packet(b"OpusHead\x01\x02\x00\x0f\x80\xbb\0\0\0\0\0").unwrap();
+		writer.write_header_packet(b"OpusTags").unwrap();
+		for _ in 0..300 {
+			writer.write_audio_packet(&[0u8; 300], 960).unwrap();
+		}
+	}
+	let (packets, error) = OggPacket::from_cursor(&mut Cursor::new(sink));
+	assert_eq!(error, None);
+	assert_eq!(packets[1].granule_position, 0);
+	let last = packets.last().unwrap();
+	assert!(last.is_last_page());
+	assert_eq!(last.granule_position, 300 * 960);
+	for packet in packets[2..].iter() {
+		// Each packet finishes some audio packets, the granule position counts the finished ones.
+		let finished: u64 = packets[2..].iter().take_while(|p|p.packet_index <= packet.packet_index).map(|p|p.segment_table.iter().filter(|&&s|s < 255).count() as u64).sum();
+		assert_eq!(packet.granule_position, finished * 960);
+	}
+}
+
+#[test]
+fn test_get_logical_packet() {
+	let mut sink = Vec::<u8>::new();
+	let logical_packets: Vec<Vec<u8>> = [30usize, 70000, 255, 10].iter().enumerate().map(|(i, &size)|vec![i as u8; size]).collect();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		for packet in logical_packets.iter() {
+			writer.write_audio_packet(packet, 960).unwrap();
+		}
+	}
+	let mut oggreader = OggStreamReader::new(Cursor::new(sink));
+	let mut reassembled = Vec::<OggLogicalPacket>::new();
+	while let Some(packet) = oggreader.get_logical_packet().unwrap() {
+		reassembled.push(packet);
+	}
+	assert_eq!(reassembled.iter().map(|p|p.data.clone()).collect::<Vec<_>>(), logical_packets);
+	assert!(reassembled[0].is_first_packet);
+	assert_eq!(reassembled[0].granule_position, 960);
+	assert_eq!(reassembled[1].granule_position, u64::MAX);
+	assert!(reassembled[3].is_last_packet);
+	assert_eq!(reassembled[3].granule_position, 4 * 960);
+
+	// A lost packet drops the logical packets that span it
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		for packet in logical_packets.iter() {
+			writer.write_audio_packet(packet, 960).unwrap();
+		}
+	}
+	let (packets, _) = OggPacket::parse_all(&sink);
+	let mut oggreader = OggStreamReader::new(Cursor::new(mux_pages(packets.into_iter().filter(|p|p.packet_index != 0))));
+	let packet = oggreader.get_logical_packet().unwrap().unwrap();
+	assert_eq!(packet.data, logical_packets[2]);
+}
+
+#[test]
+fn test_packet_per_write() {
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		writer.write_all(&[0u8; 255]).unwrap();
+		writer.write_all(b"Hello").unwrap();
+		writer.flush_page(0).unwrap();
+		writer.set_packet_per_write(true);
+		writer.write_all(&[0u8; 255]).unwrap();
+		writer.write_all(b"Hello").unwrap();
+		// The data in the current packet isn't counted until the packet is written.
+		assert_eq!(writer.get_bytes_written(), 260);
+	}
+	let mut oggreader = OggStreamReader::new(Cursor::new(sink));
+	let mut packets = Vec::<Vec<u8>>::new();
+	while let Some(packet) = oggreader.get_logical_packet().unwrap() {
+		packets.push(packet.data);
+	}
+	// In the byte stream mode, the 255 bytes aren't terminated so they are merged with the next write.
+	assert_eq!(packets, vec![[&[0u8; 255] as &[u8], b"Hello"].concat(), vec![0u8; 255], b"Hello".to_vec()]);
+}
+
+#[test]
+fn test_with_capacity() {
+	let mut packet = OggPacket::with_capacity(0x12345678, OggPacketType::BeginOfStream, 0, 60000);
+	let data_ptr = packet.data.as_ptr();
+	let table_ptr = packet.segment_table.as_ptr();
+	for _ in 0..235 {
+		assert_eq!(packet.write(&[0u8; 255]), 255);
+	}
+	assert_eq!(packet.data.as_ptr(), data_ptr);
+	assert_eq!(packet.segment_table.as_ptr(), table_ptr);
+}
+
+#[test]
+fn test_payload_reader() {
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let expected: Vec<u8> = OggPacket::parse_all(&test_ogg).0.into_iter().flat_map(|p|p.data).collect();
+	let mut oggreader = OggStreamReader::new(Cursor::new(test_ogg));
+	let mut payload = Vec::<u8>::new();
+	oggreader.payload_reader().read_to_end(&mut payload).unwrap();
+	assert_eq!(payload, expected);
+	assert!(oggreader.is_eos());
+
+	// The EOS packet of another stream doesn't end the data of the stream
+	let mut first = PacketBuilder::new(1, 0);
+	let mut second = PacketBuilder::new(2, 0);
+	let mut pages = first.build(b"first", 0, false);
+	pages.extend(second.build(b"second", 0, true));
+	for i in 1..=3u64 {
+		pages.extend(first.build(&[i as u8; 10], i, i == 3));
+	}
+	let mut oggreader = OggStreamReader::new(Cursor::new(mux_pages(pages)));
+	let mut payload = Vec::<u8>::new();
+	oggreader.payload_reader().read_to_end(&mut payload).unwrap();
+	assert_eq!(payload, [b"first".to_vec(), vec![1u8; 10], vec![2u8; 10], vec![3u8; 10]].concat());
+	assert!(oggreader.is_eos());
+}
+
+#[test]
+fn test_get_length_truncated_segment_table() {
+	let mut bytes = OggPacket::new(0x12345678, OggPacketType::BeginOfStream, 0).into_bytes();
+	bytes.truncate(27);
+	bytes[26] = 200;
+	let e = OggPacket::get_length(&bytes).unwrap_err();
+	assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+	assert!(matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::UnexpectedEof{got: 27, needed: 227})));
+	assert!(OggPacket::from_bytes_ref(&bytes).is_err());
+
+	// A segment table over 255 segments can't come from the bytes, but its sum is still bounded
+	let e = OggPacket::data_length_of(&[255u8; 256]).unwrap_err();
+	assert_eq!(e, OggError::MalformedSegmentTable{num_segments: 256, data_length: 255 * 256});
+	assert_eq!(io::Error::from(e).kind(), io::ErrorKind::InvalidData);
+	assert_eq!(OggPacket::data_length_of(&[255u8; 255]), Ok(OggPacket::MAX_PAYLOAD));
+}
+
+#[test]
+fn test_buf_reader() {
+	use std::io::BufReader;
+	let expected: Vec<OggPacket> = {
+		let mut oggreader = OggStreamReader::new(Cursor::new(std::fs::read("test.ogg").unwrap()));
+		std::iter::from_fn(||oggreader.get_packet().unwrap()).collect()
+	};
+	// A tiny buffer makes every packet straddle the end of it.
+	for capacity in [7, 4096, 1 << 20] {
+		let mut oggreader = OggBufReader::new(BufReader::with_capacity(capacity, Cursor::new(std::fs::read("test.ogg").unwrap())));
+		let mut num_packets = 0usize;
+		while let Some(packet) = oggreader.get_packet().unwrap() {
+			assert_eq!(packet.into_bytes(), expected[num_packets].clone().into_bytes());
+			num_packets += 1;
+		}
+		assert_eq!(num_packets, expected.len());
+		assert!(oggreader.is_eos());
+		assert_eq!(oggreader.stream_id, expected[0].stream_id);
+	}
+
+	let mut bytes = std::fs::read("test.ogg").unwrap();
+	bytes.truncate(bytes.len() - 10);
+	let mut oggreader = OggBufReader::new(BufReader::with_capacity(64, Cursor::new(bytes)));
+	let e = loop {
+		match oggreader.get_packet() {
+			Ok(Some(_)) => (),
+			Ok(None) => panic!("The truncated packet should be an error"),
+			Err(e) => break e,
+		}
+	};
+	assert!(matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::Truncated{..})));
+
+	// A corrupt packet straddling the end of the buffer is dropped along with its copied bytes
+	let mut bytes = Vec::<u8>::new();
+	for i in 0..3 {
+		let mut packet = OggPacket::new(0x12345678, OggPacketType::Continuation, i);
+		packet.write(b"Hello");
+		bytes.extend(packet.into_bytes());
+	}
+	let page_size = bytes.len() / 3;
+	let mut corrupt = bytes.clone();
+	corrupt[page_size * 2 - 1] ^= 1;
+	let mut oggreader = OggBufReader::new(BufReader::with_capacity(page_size + 7, Cursor::new(corrupt)));
+	assert_eq!(oggreader.get_packet().unwrap().unwrap().packet_index, 0);
+	let e = oggreader.get_packet().unwrap_err();
+	assert!(matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::ChecksumMismatch{..})));
+	assert_eq!(oggreader.get_packet().unwrap().unwrap().packet_index, 2);
+	assert!(oggreader.get_packet().unwrap().is_none());
+
+	// The capture pattern is split by the end of the buffer
+	let mut corrupt = bytes.clone();
+	corrupt[page_size + 2] = b'X';
+	let mut oggreader = OggBufReader::new(BufReader::with_capacity(page_size + 2, Cursor::new(corrupt)));
+	assert_eq!(oggreader.get_packet().unwrap().unwrap().packet_index, 0);
+	let e = oggreader.get_packet().unwrap_err();
+	assert!(matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::BadCapturePattern(_))));
+	assert_eq!(oggreader.stream_position(), page_size as u64 + 27);
+	let (_, straddle) = oggreader.into_inner();
+	assert!(straddle.is_empty());
+}
+
+#[test]
+fn test_try_on_seal() {
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriterBuilder::new()
+			.stream_id(0x12345678)
+			.max_page_payload(255)
+			.try_on_seal(|size|if size > 255 {Ok(size as u64)} else {Err(io::Error::new(ErrorKind::InvalidData, "timestamp overflow"))})
+			.build(&mut sink);
+		// The data before the failure is consumed, then the next call returns the error without consuming anything.
+		assert_eq!(writer.write(&[0u8; 300]).unwrap(), 255);
+		let e = writer.write(&[0u8; 45]).unwrap_err();
+		assert_eq!(e.kind(), ErrorKind::InvalidData);
+		assert_eq!(writer.get_bytes_written(), 0);
+		assert_eq!(writer.cur_packet.get_inner_data_size(), 255);
+		writer.set_on_seal_callback(Box::new(|size|size as u64 * 2));
+		assert_eq!(writer.write(&[0u8; 45]).unwrap(), 45);
+		assert_eq!(writer.get_granule_position(), 510);
+		assert_eq!(writer.get_bytes_written(), 255);
+	}
+	let mut oggreader = OggStreamReader::new(Cursor::new(sink));
+	let first = oggreader.get_packet().unwrap().unwrap();
+	assert_eq!(first.get_inner_data_size(), 255);
+	assert_eq!(first.granule_position, 510);
+	assert_eq!(oggreader.get_packet().unwrap().unwrap().get_inner_data_size(), 45);
+}
+
+#[test]
+fn test_page_flags() {
+	let mut packet = OggPacket::new_with_flags(0x12345678, PageFlags::BEGIN_OF_STREAM | PageFlags::END_OF_STREAM, 0);
+	assert_eq!(packet.packet_type, OggPacketType::BeginAndEndOfStream);
+	assert!(packet.is_first_page() && packet.is_last_page());
+	packet.write(b"metadata");
+	let bytes = packet.into_bytes();
+	assert_eq!(bytes[5], 0x06);
+	let packet = OggPacket::from_bytes(&bytes, &mut 0).unwrap();
+	assert_eq!(packet.flags(), PageFlags(0x06));
+	assert_eq!(packet.to_string(), "OggPage[stream=0x12345678 seq=0 flags=BOS|EOS granule=0 segs=1 bytes=8]");
+
+	let packet = OggPacket::new_with_flags(0x12345678, PageFlags::CONTINUED | PageFlags::END_OF_STREAM, 3);
+	assert_eq!(packet.packet_type, OggPacketType::EndOfStream);
+	assert!(packet.is_continued);
+	assert_eq!(packet.into_bytes()[5], 0x05);
+
+	// A stream with only one packet keeps the BOS flag on its EOS packet.
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		writer.write_all(b"metadata").unwrap();
+	}
+	let mut oggreader = OggStreamReader::new(Cursor::new(sink));
+	let packet = oggreader.get_packet().unwrap().unwrap();
+	assert_eq!(packet.packet_type, OggPacketType::BeginAndEndOfStream);
+	assert!(oggreader.get_packet().unwrap().is_none());
+}
+
+#[test]
+fn test_scan_pages() {
+	let bytes = std::fs::read("test.ogg").unwrap();
+	let (packets, _) = OggPacket::parse_all(&bytes);
+	let pages = OggPacket::scan_pages(&bytes);
+	assert_eq!(pages.len(), packets.len());
+	assert_eq!(pages.iter().map(|(_, length)|length).sum::<usize>(), bytes.len());
+	assert_eq!(OggPacket::scan_pages_checked(&bytes), pages);
+
+	// Garbage before the packets is skipped, a corrupt packet is only skipped when the checksums are verified.
+	let mut corrupt = b"garbage".to_vec();
+	corrupt.extend(&bytes);
+	let (offset, length) = pages[1];
+	corrupt[7 + offset + length - 1] ^= 0xFF;
+	let scanned = OggPacket::scan_pages(&corrupt);
+	assert_eq!(scanned.len(), pages.len());
+	assert_eq!(scanned[0], (7, pages[0].1));
+	let checked = OggPacket::scan_pages_checked(&corrupt);
+	assert_eq!(checked.len(), pages.len() - 1);
+	assert_eq!(checked[1].0, 7 + pages[2].0);
+}
+
+#[test]
+fn test_resume_mid_packet() {
+	let packet: Vec<u8> = (0..800u32).map(|i|i as u8).collect();
+	// The first session ends in the middle of the packet, the bytes are taken before the writer seals the EOS packet on drop.
+	let mut sink = {
+		let mut writer = OggStreamWriter::new(Vec::<u8>::new(), 0x12345678);
+		writer.write_all(&packet[..510]).unwrap();
+		writer.flush_page(u64::MAX).unwrap();
+		writer.writer.clone()
+	};
+	{
+		let mut writer = OggStreamWriter::resume_mid_packet(&mut sink, 0x12345678, 1, 1000);
+		writer.write_packet(&packet[510..]).unwrap();
+	}
+	let mut oggreader = OggStreamReader::new(Cursor::new(sink.clone()));
+	oggreader.set_strict(true);
+	let logical = oggreader.get_logical_packet().unwrap().unwrap();
+	assert_eq!(logical.data, packet);
+	assert_eq!(logical.granule_position, 1000);
+	assert!(logical.is_last_packet);
+	assert!(oggreader.get_logical_packet().unwrap().is_none());
+
+	let (packets, _) = OggPacket::parse_all(&sink);
+	assert_eq!(packets.len(), 2);
+	assert_eq!(packets[1].flags(), PageFlags::CONTINUED | PageFlags::END_OF_STREAM);
+	assert_eq!(packets[1].packet_index, 1);
+}
+
+#[test]
+fn test_writer_config() {
+	let config = WriterConfig {
+		stream_id: 0x12345678,
+		max_page_payload: 1000,
+		start_sequence: 5,
+		packet_per_write: true,
+	};
+	let mut sinks = [Vec::<u8>::new(), Vec::<u8>::new()];
+	for sink in sinks.iter_mut() {
+		let mut writer = OggStreamWriter::new(sink, 0);
+		config.apply(&mut writer).unwrap();
+		assert_eq!(WriterConfig::from_writer(&writer), config);
+		writer.write_all(&[1u8; 3000]).unwrap();
+	}
+	assert_eq!(sinks[0], sinks[1]);
+	let (packets, _) = OggPacket::parse_all(&sinks[0]);
+	assert_eq!(packets[0].stream_id, 0x12345678);
+	assert_eq!(packets[0].packet_index, 5);
+	assert!(!packets[0].is_first_page());
+	assert!(packets.iter().all(|p|p.get_inner_data_size() <= 1000));
+
+	let mut writer = OggStreamWriter::new(Vec::<u8>::new(), 0);
+	writer.flush_page(0).unwrap();
+	assert_eq!(config.apply(&mut writer).unwrap_err().kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_reserved_header_type_bits() {
+	let mut bytes = OggPacket::new(0x12345678, OggPacketType::BeginOfStream, 0).into_bytes();
+	bytes[5] = 0x02 | 0x20;
+	OggPacket::fill_checksum_field(&mut bytes).unwrap();
+	let packet = OggPacket::from_bytes(&bytes, &mut 0).unwrap();
+	assert_eq!(packet.packet_type, OggPacketType::BeginOfStream);
+	assert!(!packet.is_continued);
+	assert_eq!(OggPacket::get_length(&bytes).unwrap(), 27);
+
+	let mut oggreader = OggStreamReader::new(Cursor::new(bytes.clone()));
+	assert!(oggreader.get_packet().unwrap().unwrap().is_first_page());
+
+	let mut oggreader = OggStreamReader::new(Cursor::new(bytes));
+	oggreader.set_strict(true);
+	let e = oggreader.get_packet().unwrap_err();
+	assert!(matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::BadPacketType(0x22))));
+	// The empty packet with the granule position 0 doesn't finish any data either, it's reported after the first error.
+	let e = oggreader.get_packet().unwrap_err();
+	assert!(matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::UnterminatedPacket{..})));
+	assert!(oggreader.get_packet().unwrap().unwrap().is_first_page());
+}
+
+#[test]
+fn test_max_claimed_length() {
+	// The largest segment table claims the largest packet, which is bounded, so the reader never waits for more than it.
+	let mut bytes = OggPacket::new(0x12345678, OggPacketType::BeginOfStream, 0).into_bytes();
+	bytes[26] = 255;
+	bytes.extend([255u8; 255]);
+	assert_eq!(OggPacket::get_length(&bytes).unwrap(), OggPacket::MAX_PAGE_SIZE);
+	let e = OggPacket::from_bytes(&bytes, &mut 0).unwrap_err();
+	assert!(matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::UnexpectedEof{needed: OggPacket::MAX_PAGE_SIZE, ..})));
+
+	// Garbage after the claimed segment table is found by the checksum.
+	let mut garbage = bytes.clone();
+	garbage.extend(vec![0x55u8; OggPacket::MAX_PAYLOAD + 100]);
+	let mut oggreader = OggStreamReader::new(Cursor::new(garbage));
+	let e = oggreader.get_packet().unwrap_err();
+	assert!(matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::ChecksumMismatch{..})));
+
+	let mut oggreader = OggStreamReader::new(Cursor::new(bytes));
+	let e = oggreader.get_packet().unwrap_err();
+	assert!(matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::Truncated{needed: OggPacket::MAX_PAGE_SIZE, ..})));
+}
+
+#[test]
+fn test_packet_write_all() {
+	let mut packet = OggPacket::new(0x12345678, OggPacketType::BeginOfStream, 0);
+	assert_eq!(packet.write_all(b"Hello"), None);
+	assert_eq!(packet.get_inner_data(), b"Hello");
+
+	let data = vec![1u8; OggPacket::MAX_PAYLOAD + 1000];
+	let mut packet = OggPacket::new(0x12345678, OggPacketType::BeginOfStream, 0);
+	let rest = packet.write_all(&data).unwrap();
+	assert_eq!(rest.len(), 1000);
+	assert_eq!(packet.get_inner_data_size(), OggPacket::MAX_PAYLOAD);
+	assert_eq!(packet.write_all(rest), Some(rest));
+
+	let mut packet = OggPacket::new(0x12345678, OggPacketType::BeginOfStream, 0);
+	let e = Write::write_all(&mut packet, &data).unwrap_err();
+	assert_eq!(e.kind(), ErrorKind::WriteZero);
+	assert_eq!(packet.get_inner_data_size(), OggPacket::MAX_PAYLOAD);
+	assert_eq!(Write::write(&mut packet, b"").unwrap(), 0);
+}
+
+#[test]
+fn test_filter_stream() {
+	let mut muxer = OggMuxer::new(Vec::<u8>::new());
+	for stream_id in 1..=3u32 {
+		muxer.add_stream(stream_id, Codec::Vorbis{sample_rate: 48000}).unwrap();
+	}
+	for i in 0..20u64 {
+		for stream_id in 1..=3u32 {
+			// The streams end at different times, the EOS of the first one comes before the end of the middle one.
+			if i < 10 * stream_id as u64 {
+				muxer.push_packet(stream_id, &[stream_id as u8; 100], i * 1000).unwrap();
+			}
+		}
+	}
+	let sink = muxer.finish().unwrap();
+	let packets: Vec<OggPacket> = OggStreamReader::filter_stream(Cursor::new(sink), 2).collect::<io::Result<_>>().unwrap();
+	assert!(packets.iter().all(|p|p.stream_id == 2));
+	assert!(packets[0].is_first_page());
+	assert!(packets.last().unwrap().is_last_page());
+	assert_eq!(packets.iter().filter(|p|!p.data.is_empty()).count(), 20);
+	assert!(packets.iter().all(|p|p.data.iter().all(|&b|b == 2)));
+}
+
+#[test]
+fn test_parse_limits() {
+	let mut packet = OggPacket::new(0x12345678, OggPacketType::BeginOfStream, 0);
+	packet.write(&[1u8; 5000]);
+	let bytes = packet.into_bytes();
+	let limits = ParseLimits{max_page_size: 4096};
+	let mut packet_length = 0usize;
+	let e = OggPacket::from_bytes_with_limits(&bytes, &mut packet_length, limits).unwrap_err();
+	assert!(matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::BufferOverflow{limit: 4096, ..})));
+	assert_eq!(packet_length, bytes.len());
+	// Only the header is needed to reject the packet.
+	assert!(OggPacket::from_bytes_with_limits(&bytes[..100], &mut packet_length, limits).is_err_and(|e|e.kind() == ErrorKind::InvalidData));
+	assert!(OggPacket::from_bytes_with_limits(&bytes, &mut packet_length, ParseLimits::default()).is_ok());
+
+	// The parsers don't panic on the truncated or corrupt bytes.
+	for i in 0..bytes.len().min(300) {
+		let mut corrupt = bytes.clone();
+		corrupt[i] ^= 0xFF;
+		for data in [&bytes[..i], &corrupt[..]] {
+			let _ = OggPacket::from_bytes_with_limits(data, &mut 0, limits);
+			let _ = OggPacket::from_cursor(&mut Cursor::new(data.to_vec()));
+		}
+	}
+}
+
+#[test]
+fn test_parse_header() {
+	let bytes = std::fs::read("test.ogg").unwrap();
+	let mut offset = 0usize;
+	while offset < bytes.len() {
+		let header = OggPacket::parse_header(&bytes[offset..]).unwrap();
+		let mut packet_length = 0usize;
+		let packet = OggPacket::from_bytes(&bytes[offset..], &mut packet_length).unwrap();
+		assert_eq!(header.page_length, packet_length);
+		assert_eq!(header.data_length(), packet.data.len());
+		assert_eq!(header.flags, packet.flags());
+		assert_eq!(header.granule_position, packet.granule_position);
+		assert_eq!(header.stream_id, packet.stream_id);
+		assert_eq!(header.packet_index, packet.packet_index);
+		assert_eq!(header.checksum, packet.checksum);
+		assert_eq!(header.num_segments as usize, packet.segment_table.len());
+		// The header alone is enough.
+		let header_length = OggPacket::HEADER_SIZE + header.num_segments as usize;
+		assert_eq!(OggPacket::parse_header(&bytes[offset..offset + header_length]).unwrap(), header);
+		offset += packet_length;
+	}
+	assert!(OggPacket::parse_header(&bytes[..26]).is_err());
+}
+
+#[test]
+fn test_ogg_crc() {
+	let bytes = std::fs::read("test.ogg").unwrap();
+	let length = OggPacket::get_length(&bytes).unwrap();
+	let page = &bytes[..length];
+	let mut zeroed = page.to_vec();
+	zeroed[22..26].fill(0);
+	let mut crc = OggCrc::new();
+	crc.update(&zeroed);
+	assert_eq!(crc.finalize(), OggPacket::get_checksum(page).unwrap());
+	assert_eq!(crc.finalize().to_le_bytes(), page[22..26]);
+
+	// Verify while copying in small chunks.
+	let mut crc = OggCrc::new();
+	let mut copied = Vec::<u8>::new();
+	for chunk in zeroed.chunks(7) {
+		crc.update(chunk);
+		copied.extend(chunk);
+	}
+	assert_eq!(crc.finalize(), OggPacket::get_checksum(page).unwrap());
+	assert_eq!(copied, zeroed);
+	assert_eq!(OggCrc::new().finalize(), 0);
+}
+
+#[test]
+fn test_skip_to_stream() {
+	let mut sink = Vec::<u8>::new();
+	for stream_id in 1..=3u32 {
+		let mut writer = OggStreamWriter::new(&mut sink, stream_id);
+		writer.write_packet(&[stream_id as u8; 100]).unwrap();
+		writer.flush_page(0).unwrap();
+	}
+	let mut oggreader = OggStreamReader::new(Cursor::new(sink));
+	assert!(oggreader.skip_to_stream(3).unwrap());
+	assert!(oggreader.skip_to_stream(3).unwrap());
+	let packet = oggreader.get_packet().unwrap().unwrap();
+	assert_eq!(packet.stream_id, 3);
+	assert!(packet.is_first_page());
+	assert!(!oggreader.skip_to_stream(1).unwrap());
+}
+
+#[test]
+fn test_seal_page_no_completion() {
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		writer.set_granule_position(1000);
+		// A part of a large header packet, it doesn't finish there.
+		writer.write_all(&[5u8; 510]).unwrap();
+		writer.seal_page_no_completion().unwrap();
+		assert_eq!(writer.get_granule_position(), 1000);
+		writer.write_all(&[5u8; 100]).unwrap();
+		writer.seal_packet(writer.get_granule_position(), false).unwrap();
+	}
+	let (packets, _) = OggPacket::parse_all(&sink);
+	assert_eq!(packets[0].granule_position, u64::MAX);
+	assert_eq!(packets[0].segment_table, [255, 255]);
+	assert_eq!(packets[1].granule_position, 1000);
+	assert_eq!(packets[1].segment_table, [100]);
+}
+
+#[test]
+fn test_packet_eq_hash() {
+	use std::hash::{BuildHasher, RandomState};
+	let bytes = std::fs::read("test.ogg").unwrap();
+	let a = OggPacket::from_bytes(&bytes, &mut 0).unwrap();
+	let b = OggPacket::from_bytes(&bytes.clone(), &mut 0).unwrap();
+	let hasher = RandomState::new();
+	assert_eq!(a, b);
+	assert_eq!(hasher.hash_one(&a), hasher.hash_one(&b));
+
+	// The stored checksum doesn't matter, the contents do.
+	let mut c = b.clone();
+	c.checksum ^= 0xFFFFFFFF;
+	assert_eq!(a, c);
+	assert_eq!(hasher.hash_one(&a), hasher.hash_one(&c));
+	c.granule_position += 1;
+	assert_ne!(a, c);
+	let pages: HashSet<OggPacket> = [a, b, c].into_iter().collect();
+	assert_eq!(pages.len(), 2);
+}
+
+#[test]
+fn test_relabel() {
+	let bytes = std::fs::read("test.ogg").unwrap();
+	let packet = OggPacket::from_bytes(&bytes, &mut 0).unwrap();
+	let relabeled = packet.clone().with_stream_id(0x12345678).with_sequence(42).into_bytes();
+	let reparsed = OggPacket::from_bytes(&relabeled, &mut 0).unwrap();
+	assert_eq!(reparsed.stream_id, 0x12345678);
+	assert_eq!(reparsed.packet_index, 42);
+	assert_eq!(reparsed.data, packet.data);
+	assert_eq!(reparsed.flags(), packet.flags());
+	assert_ne!(reparsed.checksum, packet.checksum);
+}
+
+#[test]
+fn test_checked_add_granule() {
+	let mut sink = Vec::<u8>::new();
+	let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+	writer.set_granule_position(u64::MAX - 10);
+	writer.checked_add_granule(9).unwrap();
+	assert_eq!(writer.get_granule_position(), u64::MAX - 1);
+	assert_eq!(writer.checked_add_granule(1).unwrap_err().kind(), ErrorKind::InvalidInput);
+	assert_eq!(writer.checked_add_granule(100).unwrap_err().kind(), ErrorKind::InvalidInput);
+	assert_eq!(writer.get_granule_position(), u64::MAX - 1);
+	let written = writer.cur_packet.get_inner_data_size();
+	assert!(writer.write_audio_packet(&[1u8; 100], 960).is_err());
+	assert_eq!(writer.cur_packet.get_inner_data_size(), written);
+	writer.set_granule_position(0);
+	writer.write_audio_packet(&[1u8; 100], 960).unwrap();
+	assert_eq!(writer.get_granule_position(), 960);
+}
+
+#[test]
+fn test_on_page_written() {
+	use std::{cell::RefCell, rc::Rc};
+	let pages = Rc::new(RefCell::new(Vec::<PageInfo>::new()));
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		let pages = pages.clone();
+		writer.set_on_page_written_callback(Box::new(move |info|pages.borrow_mut().push(*info)));
+		writer.write_packet(&[1u8; 100]).unwrap();
+		writer.flush_page(100).unwrap();
+		writer.write_packet(&[2u8; 70000]).unwrap();
+	}
+	let pages = pages.borrow();
+	let (packets, _) = OggPacket::parse_all(&sink);
+	assert_eq!(pages.len(), packets.len());
+	assert_eq!(pages.iter().map(|p|p.byte_len).sum::<usize>(), sink.len());
+	for (info, packet) in pages.iter().zip(packets.iter()) {
+		assert_eq!(info.sequence, packet.packet_index);
+		assert_eq!(info.granule, packet.granule_position);
+		assert_eq!(info.is_eos, packet.is_last_page());
+	}
+	assert_eq!(pages[0], PageInfo{stream_id: 0x12345678, sequence: 0, flags: PageFlags::BEGIN_OF_STREAM, granule: 100, payload_len: 100, segment_count: 1, byte_len: 27 + 1 + 100, is_eos: false});
+	for (info, packet) in pages.iter().zip(packets.iter()) {
+		assert_eq!(*info, packet.as_page_info());
+	}
+	assert!(pages.last().unwrap().is_eos);
+}
+
+#[test]
+fn test_write_empty_packet() {
+	let mut packet = OggPacket::new(0x12345678, OggPacketType::BeginAndEndOfStream, 0);
+	assert_eq!(packet.write(b""), 0);
+	assert!(packet.segment_table.is_empty());
+	packet.write(b"abc");
+	assert!(packet.write_empty_packet());
+	packet.write(b"de");
+	let bytes = packet.into_bytes();
+	let packet = OggPacket::from_bytes(&bytes, &mut 0).unwrap();
+	assert_eq!(packet.segment_table, [3, 0, 2]);
+	assert_eq!(packet.get_segments(), [b"abc".to_vec(), Vec::new(), b"de".to_vec()]);
+
+	let mut reader = OggStreamReader::new(Cursor::new(bytes));
+	let logical_packets: Vec<Vec<u8>> = std::iter::from_fn(||reader.get_logical_packet().unwrap()).map(|p|p.data).collect();
+	assert_eq!(logical_packets, [b"abc".to_vec(), Vec::new(), b"de".to_vec()]);
+
+	let mut packet = OggPacket::new(0x12345678, OggPacketType::Continuation, 1);
+	for _ in 0..255 {
+		assert!(packet.write_empty_packet());
+	}
+	assert!(!packet.write_empty_packet());
+	assert_eq!(packet.get_segments().len(), 255);
+}
+
+#[test]
+fn test_strict_bos() {
+	fn strict_errors(pages: &[OggPacket]) -> (usize, Vec<OggError>) {
+		let mut reader = OggStreamReader::new(Cursor::new(mux_pages(pages.to_vec())));
+		reader.set_strict(true);
+		reader.set_continue_after_eos(true);
+		let mut num_packets = 0;
+		let mut errors = Vec::<OggError>::new();
+		loop {
+			match reader.get_packet() {
+				Ok(Some(_)) => num_packets += 1,
+				Ok(None) => break,
+				Err(e) => errors.push(e.into_inner().unwrap().downcast::<OggError>().map(|e|*e).unwrap()),
+			}
+		}
+		(num_packets, errors)
+	}
+	let page = |packet_type, packet_index| {
+		let mut page = OggPacket::new(0x12345678, packet_type, packet_index);
+		page.write(b"data");
+		page
+	};
+
+	// A stream read from its middle
+	let (num_packets, errors) = strict_errors(&[page(OggPacketType::Continuation, 5), page(OggPacketType::EndOfStream, 6)]);
+	assert_eq!(num_packets, 2);
+	assert_eq!(errors, vec![OggError::MissingBos{stream_id: 0x12345678, packet_index: 5}]);
+
+	// A BOS packet in the middle of a stream, it still restarts the sequence for the packets after it
+	let (num_packets, errors) = strict_errors(&[page(OggPacketType::BeginOfStream, 0), page(OggPacketType::Continuation, 1), page(OggPacketType::BeginOfStream, 0), page(OggPacketType::Continuation, 1)]);
+	assert_eq!(num_packets, 4);
+	assert_eq!(errors, vec![OggError::BosNotFirst{stream_id: 0x12345678, packet_index: 0}]);
+
+	// A chained stream reusing the stream ID is fine, but not a packet after the EOS packet without a BOS packet
+	let (num_packets, errors) = strict_errors(&[page(OggPacketType::BeginOfStream, 0), page(OggPacketType::EndOfStream, 1), page(OggPacketType::BeginOfStream, 0), page(OggPacketType::EndOfStream, 1)]);
+	assert_eq!((num_packets, errors), (4, vec![]));
+	let (_, errors) = strict_errors(&[page(OggPacketType::BeginAndEndOfStream, 0), page(OggPacketType::EndOfStream, 1)]);
+	assert_eq!(errors, vec![OggError::MissingBos{stream_id: 0x12345678, packet_index: 1}]);
+
+	// The first packet of a stream is a fresh packet
+	let mut continued = page(OggPacketType::BeginOfStream, 0);
+	continued.is_continued = true;
+	let (num_packets, errors) = strict_errors(&[continued]);
+	assert_eq!(num_packets, 1);
+	assert_eq!(errors, vec![OggError::BadPacketType(3)]);
+}
+
+#[test]
+fn test_write_to() {
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let (mut packets, _) = OggPacket::parse_all(&test_ogg);
+	packets[1].checksum = 0;
+	let mut sink = Vec::<u8>::new();
+	for packet in packets.iter() {
+		assert_eq!(packet.to_bytes(), packet.clone().into_bytes());
+		packet.write_to(&mut sink).unwrap();
+	}
+	assert_eq!(sink, test_ogg);
+	// The checksum field is recalculated, not written as is
+	assert_eq!(packets[1].checksum, 0);
+	packets[1].recompute_checksum();
+	assert_eq!(packets[1].to_bytes()[22..26], test_ogg[packets[0].total_page_size() + 22..packets[0].total_page_size() + 26]);
+}
+
+#[test]
+fn test_partition_stream() {
+	let mut oggreader = OggStreamReader::new(Cursor::new(std::fs::read("test.ogg").unwrap()));
+	let (headers, data_packets) = partition_stream(&mut oggreader, 1201010866).unwrap();
+	let data_packets: Vec<OggPacket> = data_packets.map(|p|p.unwrap()).collect();
+	assert!(headers[0].is_first_page());
+	assert!(headers.iter().all(|p|p.granule_position == 0 || p.granule_position == u64::MAX));
+	assert!(data_packets[0].granule_position != 0);
+	assert!(data_packets.last().unwrap().is_last_page());
+	let (all, _) = OggPacket::parse_all(&std::fs::read("test.ogg").unwrap());
+	assert_eq!(headers.len() + data_packets.len(), all.len());
+
+	// The packets of the other streams are skipped, the -1 packets go with the packets after them
+	let mut pages = Vec::<OggPacket>::new();
+	let mut builder = PacketBuilder::new(1, 0);
+	let mut other = PacketBuilder::new(2, 0);
+	pages.extend(builder.build(b"header", 0, false));
+	pages.extend(other.build(b"other header", 0, true));
+	pages.extend(builder.build(&[0u8; 70000], 0, false));
+	pages.extend(builder.build(&[1u8; 70000], 1000, false));
+	pages.extend(builder.build(&[2u8; 100], 2000, true));
+	let mut oggreader = OggStreamReader::new(Cursor::new(mux_pages(pages)));
+	let (headers, data_packets) = partition_stream(&mut oggreader, 1).unwrap();
+	let granules: Vec<u64> = data_packets.map(|p|p.unwrap().granule_position).collect();
+	assert_eq!(headers.iter().map(|p|p.granule_position).collect::<Vec<u64>>(), [0, u64::MAX, 0]);
+	assert_eq!(granules, [u64::MAX, 1000, 2000]);
+}
+
+#[test]
+fn test_checksum_mismatch_offset() {
+	let mut test_ogg = std::fs::read("test.ogg").unwrap();
+	let second = OggPacket::get_length(&test_ogg).unwrap();
+	let third = second + OggPacket::get_length(&test_ogg[second..]).unwrap();
+	test_ogg[third - 1] ^= 0xFF;
+	let offset_of = |e: &OggError|match e {
+		OggError::ChecksumMismatch{offset, ..} => Some(*offset),
+		_ => None,
+	};
+
+	let mut oggreader = OggStreamReader::new(Cursor::new(test_ogg.clone()));
+	oggreader.get_packet().unwrap();
+	let e = oggreader.get_packet().unwrap_err();
+	assert_eq!(offset_of(e.get_ref().unwrap().downcast_ref::<OggError>().unwrap()), Some(second as u64));
+	assert!(e.to_string().contains(&format!("at offset {second}")));
+
+	let mut oggreader = OggBufReader::new(std::io::BufReader::with_capacity(100, Cursor::new(test_ogg.clone())));
+	oggreader.get_packet().unwrap();
+	let e = oggreader.get_packet().unwrap_err();
+	assert_eq!(offset_of(e.get_ref().unwrap().downcast_ref::<OggError>().unwrap()), Some(second as u64));
+
+	let mut cursor = Cursor::new(test_ogg.clone());
+	let (packets, error) = OggPacket::from_cursor(&mut cursor);
+	assert_eq!(packets.len(), 1);
+	assert_eq!(error.as_ref().and_then(offset_of), Some(second as u64));
+	// The offset counts from the beginning of the cursor, not from where the parsing started
+	let (_, error) = OggPacket::from_cursor(&mut cursor);
+	assert_eq!(error.as_ref().and_then(offset_of), Some(second as u64));
+}
+
+#[test]
+fn test_with_stream_id() {
+	let mut pages = Vec::<OggPacket>::new();
+	let mut audio = PacketBuilder::new(1, 0);
+	let mut other = PacketBuilder::new(2, 0);
+	pages.extend(audio.build(b"header", 0, false));
+	pages.extend(other.build(b"other header", 0, false));
+	pages.extend(other.build(b"other data", 100, true));
+	pages.extend(audio.build(&[1u8; 70000], 1000, false));
+	pages.extend(audio.build(b"last", 2000, true));
+	let bytes = mux_pages(pages);
+
+	let mut oggreader = OggStreamReader::with_stream_id(Cursor::new(bytes.clone()), 1);
+	assert_eq!(oggreader.stream_id, 1);
+	let packets: Vec<OggPacket> = std::iter::from_fn(||oggreader.get_packet().unwrap()).collect();
+	assert!(packets.iter().all(|p|p.stream_id == 1));
+	assert_eq!(packets.iter().map(|p|p.packet_index).collect::<Vec<u32>>(), [0, 1, 2, 3]);
+	assert!(oggreader.is_eos());
+
+	let mut oggreader = OggStreamReader::with_stream_id(Cursor::new(bytes), 1);
+	oggreader.set_strict(true);
+	let logical: Vec<Vec<u8>> = std::iter::from_fn(||oggreader.get_logical_packet().unwrap()).map(|p|p.data).collect();
+	assert_eq!(logical, [b"header".to_vec(), vec![1u8; 70000], b"last".to_vec()]);
+}
+
+#[test]
+fn test_skip_leading_junk() {
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let (expected, _) = OggPacket::parse_all(&test_ogg);
+	// The garbage has a fake capture pattern, which is rejected by the checksum
+	let mut junk: Vec<u8> = (0..128u32).map(|i|(i * 7) as u8).collect();
+	junk[10..14].copy_from_slice(b"OggS");
+	junk.extend(&test_ogg);
+
+	let mut oggreader = OggStreamReader::new(Cursor::new(junk.clone()));
+	assert!(oggreader.get_packet().is_err());
+
+	let mut oggreader = OggStreamReader::new(Cursor::new(junk.clone()));
+	oggreader.set_read_size(64);
+	oggreader.set_skip_leading_junk(true);
+	let mut offsets = Vec::<u64>::new();
+	let mut packets = Vec::<OggPacket>::new();
+	while let Some((offset, packet)) = oggreader.get_packet_with_offset().unwrap() {
+		offsets.push(offset);
+		packets.push(packet);
+	}
+	assert_eq!(packets, expected);
+	assert_eq!(offsets[0], 128);
+	assert_eq!(oggreader.stream_position(), junk.len() as u64);
+
+	// Without any packet, the error is returned
+	let mut oggreader = OggStreamReader::new(Cursor::new(vec![0x55u8; 1000]));
+	oggreader.set_skip_leading_junk(true);
+	let e = oggreader.get_packet().unwrap_err();
+	assert!(matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::BadCapturePattern(_))));
+}
+
+#[test]
+fn test_compaction_seam() {
+	// A reader giving a byte at a time, so the packets straddle the point where the cache is compacted at any offset
+	#[derive(Debug)]
+	struct ByteReader(Cursor<Vec<u8>>);
+	impl Read for ByteReader {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			let size = buf.len().min(1);
+			self.0.read(&mut buf[..size])
+		}
+	}
+	let mut builder = PacketBuilder::new(0x12345678, 0);
+	let pages: Vec<OggPacket> = (0..200usize).flat_map(|i|builder.build(&vec![i as u8; i * 37 % 700], i as u64, i == 199)).collect();
+	let bytes = mux_pages(pages);
+	let (expected, _) = OggPacket::parse_all(&bytes);
+	for read_size in [27usize, 100, 2048] {
+		let mut oggreader = OggStreamReader::new(ByteReader(Cursor::new(bytes.clone())));
+		oggreader.set_read_size(read_size);
+		let mut offsets = Vec::<u64>::new();
+		let mut packets = Vec::<OggPacket>::new();
+		while let Some((offset, packet)) = oggreader.get_packet_with_offset().unwrap() {
+			offsets.push(offset);
+			packets.push(packet);
+		}
+		assert_eq!(packets, expected);
+		assert_eq!(offsets, OggPacket::scan_pages(&bytes).iter().map(|&(offset, _)|offset as u64).collect::<Vec<u64>>());
+	}
+}
+
+#[test]
+fn test_refill_large_packets() {
+	#[derive(Debug)]
+	struct CountingReader(Cursor<Vec<u8>>, usize);
+	impl Read for CountingReader {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			self.1 += 1;
+			self.0.read(buf)
+		}
+	}
+	let count_reads = |bytes: &[u8], read_size: usize| {
+		let mut oggreader = OggStreamReader::new(CountingReader(Cursor::new(bytes.to_vec()), 0));
+		oggreader.set_read_size(read_size);
+		let mut num_packets = 0usize;
+		while oggreader.get_packet().unwrap().is_some() {
+			num_packets += 1;
+		}
+		(num_packets, oggreader.reader.1)
+	};
+	// A packet is read with its header and segment table, then the rest, instead of refilling by `read_size` until the segment table is complete.
+	let mut builder = PacketBuilder::new(0x12345678, 0);
+	let bytes = mux_pages(builder.build(&vec![1u8; OggPacket::MAX_PAYLOAD - 1], 0, true));
+	assert_eq!(count_reads(&bytes, 27), (1, 4));
+	// Each large packet is read in one go after the first one, along with the header of the next packet.
+	let mut builder = PacketBuilder::new(0x12345678, 0);
+	let pages: Vec<OggPacket> = (0..10u64).flat_map(|i|builder.build(&vec![i as u8; OggPacket::MAX_PAYLOAD - 1], i, i == 9)).collect();
+	let bytes = mux_pages(pages);
+	assert_eq!(count_reads(&bytes, 2048), (10, 12));
+	assert_eq!(count_reads(&bytes, 27), (10, 13));
+}
+
+#[test]
+fn test_write_after_eos() {
+	let is_write_after_eos = |e: io::Error| e.kind() == ErrorKind::InvalidInput && matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::WriteAfterEos{stream_id: 0x12345678}));
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		writer.write_all(b"data").unwrap();
+		writer.mark_cur_packet_as_end_of_stream();
+		assert!(writer.is_ended());
+		assert!(is_write_after_eos(writer.write(b"more").unwrap_err()));
+		assert!(is_write_after_eos(writer.write_packet(b"more").unwrap_err()));
+		assert!(is_write_after_eos(writer.write_header_packet(b"more").unwrap_err()));
+		writer.seal_packet(4, false).unwrap();
+		assert!(is_write_after_eos(writer.write(b"more").unwrap_err()));
+		assert!(is_write_after_eos(writer.flush_page(4).unwrap_err()));
+	}
+	// Nothing is written after the EOS packet, dropping the writer doesn't write another one.
+	let (packets, consumed) = OggPacket::parse_all(&sink);
+	assert_eq!(consumed, sink.len());
+	assert_eq!(packets.len(), 1);
+	assert!(packets[0].is_last_page());
+	assert_eq!(packets[0].get_inner_data(), b"data");
+
+	// `reset()` begins a new stream
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		writer.seal_packet(0, true).unwrap();
+		assert!(writer.write(b"more").is_err());
+		writer.reset();
+		assert!(!writer.is_ended());
+		writer.write_all(b"more").unwrap();
+	}
+	let (packets, _) = OggPacket::parse_all(&sink);
+	assert_eq!(packets.len(), 2);
+	assert!(packets.iter().all(|p|p.is_first_page() && p.is_last_page()));
+	assert_eq!(packets[1].get_inner_data(), b"more");
+}
+
+#[test]
+fn test_write_page() {
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let (pages, _) = OggPacket::parse_all(&test_ogg);
+	let stream_id = pages[0].stream_id;
+
+	// Copying a file packet by packet gives the same bytes
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, stream_id);
+		let infos = std::rc::Rc::new(std::cell::RefCell::new(Vec::<PageInfo>::new()));
+		let infos_clone = infos.clone();
+		writer.set_on_page_written_callback(Box::new(move |info|infos_clone.borrow_mut().push(*info)));
+		writer.write_pages(pages.clone()).unwrap();
+		assert!(writer.is_ended());
+		assert!(writer.write_page(pages[1].clone()).is_err());
+		assert_eq!(*infos.borrow(), pages.iter().map(|p|p.as_page_info()).collect::<Vec<PageInfo>>());
+	}
+	assert_eq!(sink, test_ogg);
+
+	// Renumbered to the stream ID and the packet index of the writer, after the buffered data is sealed.
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		writer.set_page_sequence(10);
+		writer.write_all(b"buffered").unwrap();
+		writer.write_pages(pages[1..].iter().cloned()).unwrap();
+	}
+	let (copied, consumed) = OggPacket::parse_all(&sink);
+	assert_eq!(consumed, sink.len());
+	assert_eq!(copied.len(), pages.len());
+	assert_eq!(copied[0].get_inner_data(), b"buffered");
+	for (i, (copied, page)) in copied[1..].iter().zip(pages[1..].iter()).enumerate() {
+		assert_eq!(copied.stream_id, 0x12345678);
+		assert_eq!(copied.packet_index, 11 + i as u32);
+		assert_eq!(copied.flags(), page.flags());
+		assert_eq!(copied.granule_position, page.granule_position);
+		assert_eq!(copied.data, page.data);
+	}
+	assert!(copied.last().unwrap().is_last_page());
+
+	// Without the renumbering, the packets keep their indices, and the packets after them follow the last one
+	let mut writer = OggStreamWriter::new(Vec::<u8>::new(), 0x12345678);
+	writer.set_renumber_pages(false);
+	writer.write_page(pages[0].clone()).unwrap();
+	writer.write_page(pages[3].clone()).unwrap();
+	writer.write_packet(b"data").unwrap();
+	writer.seal_packet(0, true).unwrap();
+	let (copied, _) = OggPacket::parse_all(&writer.writer);
+	assert_eq!(copied.iter().map(|p|p.packet_index).collect::<Vec<u32>>(), vec![0, 3, 4]);
+	assert!(copied.iter().all(|p|p.stream_id == 0x12345678));
+
+	// The packet after a page ending with a 255 segment continues it
+	let mut writer = OggStreamWriter::new(Vec::<u8>::new(), 0x12345678);
+	let mut page = OggPacket::new(0x12345678, OggPacketType::BeginOfStream, 0);
+	page.write(&[1u8; 510]);
+	assert_eq!(page.segment_table.last(), Some(&255));
+	writer.write_page(page).unwrap();
+	writer.write_packet(b"end").unwrap();
+	writer.seal_packet(0, true).unwrap();
+	let (copied, _) = OggPacket::parse_all(&writer.writer);
+	assert_eq!(copied.len(), 2);
+	assert!(copied[1].is_continued);
+	assert_eq!(copied[1].get_inner_data(), b"end");
+}
+
+#[test]
+fn test_bytes_written() {
+	// Every way of writing counts the data of the packets written to the sink
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let (pages, _) = OggPacket::parse_all(&test_ogg);
+	let mut writer = OggStreamWriter::new(Vec::<u8>::new(), 0x12345678);
+	writer.write_header_packet(b"header").unwrap();
+	writer.write_packet(&[1u8; 70000]).unwrap();
+	writer.write_audio_packet(&[2u8; 100], 960).unwrap();
+	writer.write_all(&[3u8; 1000]).unwrap();
+	writer.write_page(pages[1].clone()).unwrap();
+	writer.write_all(&[4u8; 100000]).unwrap();
+	writer.seal_packet(0, true).unwrap();
+	let data_size: usize = OggPacket::parse_all(&writer.writer).0.iter().map(|p|p.data.len()).sum();
+	assert_eq!(writer.get_bytes_written(), data_size as u64);
+	assert_eq!(data_size, 6 + 70000 + 100 + 1000 + pages[1].data.len() + 100000);
+}
+
+#[test]
+fn test_continue_after_eos() {
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let mut builder = PacketBuilder::new(0x12345678, 0);
+	let mut chained = test_ogg.clone();
+	chained.extend(mux_pages(builder.build(b"\x01vorbis second", 0, false).into_iter().chain(builder.build(&[1u8; 1000], 960, true))));
+
+	// The reader stops at the EOS packet of the first stream by default
+	let mut oggreader = OggStreamReader::new(Cursor::new(chained.clone()));
+	oggreader.set_read_size(27);
+	let mut num_packets = 0usize;
+	while oggreader.get_packet().unwrap().is_some() {
+		num_packets += 1;
+	}
+	assert_eq!(num_packets, 5);
+	assert_eq!(oggreader.stream_id, 1201010866);
+
+	let mut oggreader = OggStreamReader::new(Cursor::new(chained.clone()));
+	oggreader.set_read_size(27);
+	oggreader.set_continue_after_eos(true);
+	let mut stream_ids = Vec::<u32>::new();
+	while let Some(packet) = oggreader.get_packet().unwrap() {
+		assert_eq!(oggreader.stream_id, packet.stream_id);
+		assert_eq!(oggreader.is_eos(), packet.is_last_page());
+		stream_ids.push(packet.stream_id);
+	}
+	assert_eq!(stream_ids, [[1201010866u32; 5].as_slice(), &[0x12345678; 2]].concat());
+	assert_eq!(oggreader.stream_position(), chained.len() as u64);
+	assert!(oggreader.is_eos());
+}
+
+#[test]
+fn test_compute_checksum() {
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let (pages, _) = OggPacket::parse_all(&test_ogg);
+	let mut builder = PacketBuilder::new(0x12345678, 0);
+	let built = builder.build(&(0..100000u32).map(|i|i as u8).collect::<Vec<u8>>(), 960, true);
+	for page in pages.iter().chain(built.iter()) {
+		// The same bytes as serializing the header and the data then filling the checksum field
+		let mut expected = page.header_bytes();
+		expected.extend(&page.data);
+		OggPacket::fill_checksum_field(&mut expected).unwrap();
+		let bytes = page.to_bytes();
+		assert_eq!(bytes, expected);
+		assert_eq!(bytes.capacity(), page.total_page_size());
+		assert_eq!(page.compute_checksum(), OggPacket::get_checksum(&bytes).unwrap());
+		let mut modified = page.clone();
+		modified.checksum = 0;
+		assert_eq!(modified.compute_checksum(), page.compute_checksum());
+	}
+	assert_eq!(mux_pages(pages), test_ogg);
+}
+
+#[test]
+fn test_any_version() {
+	// The version byte is parsed, not assumed
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let mut packet_length = 0usize;
+	let parsed = OggPacket::from_bytes(&test_ogg, &mut packet_length).unwrap();
+	assert_eq!(parsed.version, test_ogg[4]);
+	assert_eq!(parsed.version, 0);
+	assert_eq!(parsed.to_bytes(), test_ogg[..packet_length]);
+
+	let mut packet = OggPacket::new(0x12345678, OggPacketType::BeginAndEndOfStream, 0);
+	packet.write(b"data");
+	packet.version = 1;
+	let bytes = packet.to_bytes();
+	assert!(matches!(OggPacket::from_bytes(&bytes, &mut 0).unwrap_err().get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::BadVersion(1))));
+	let mut packet_length = 0usize;
+	let parsed = OggPacket::from_bytes_any_version(&bytes, &mut packet_length).unwrap();
+	assert_eq!(packet_length, bytes.len());
+	assert_eq!(parsed.version, 1);
+	assert_eq!(parsed.to_bytes(), bytes);
+	assert!(matches!(OggPacket::get_length(&bytes).unwrap_err().get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::BadVersion(1))));
+	assert_eq!(OggPacket::get_length_any_version(&bytes).unwrap(), bytes.len());
+	assert_eq!(OggPacket::parse_header(&bytes).unwrap().version, 1);
+	assert_eq!(OggPacket::from_bytes_ref(&bytes).unwrap().1, b"data");
+
+	let read = |accept_any_version: bool, strict: bool| {
+		let mut oggreader = OggStreamReader::new(Cursor::new(bytes.clone()));
+		oggreader.set_accept_any_version(accept_any_version);
+		oggreader.set_strict(strict);
+		oggreader.get_packet()
+	};
+	assert_eq!(read(true, false).unwrap().unwrap().version, 1);
+	for (accept_any_version, strict) in [(false, false), (true, true)] {
+		let e = read(accept_any_version, strict).unwrap_err();
+		assert_eq!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(&OggError::BadVersion(1)));
+	}
+}
+
+#[test]
+fn test_packets_with_pages() {
+	let mut builder = PacketBuilder::new(0x12345678, 0);
+	let mut pages = builder.build(b"header", 0, false);
+	pages.extend(builder.build(&vec![1u8; 70000], 960, false));
+	pages.extend(builder.build(&[], 960, false));
+	pages.extend(builder.build(&[2u8; 10], 1920, true));
+	let mut oggreader = OggStreamReader::new(Cursor::new(mux_pages(pages)));
+	let packets: Vec<(Vec<u8>, Vec<u32>)> = oggreader.packets_with_pages().collect::<io::Result<_>>().unwrap();
+	assert_eq!(packets.iter().map(|(data, _)|data.len()).collect::<Vec<usize>>(), vec![6, 70000, 0, 10]);
+	assert_eq!(packets.iter().map(|(_, pages)|pages.clone()).collect::<Vec<Vec<u32>>>(), vec![vec![0], vec![1, 2], vec![3], vec![4]]);
+
+	// The logical packets span consecutive packets in order, a packet finishing one and beginning the next belongs to both
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let mut oggreader = OggStreamReader::new(Cursor::new(test_ogg));
+	let mut last_page = 0u32;
+	for packet in oggreader.packets_with_pages() {
+		let (_, pages) = packet.unwrap();
+		assert!(pages[0] >= last_page);
+		assert!(pages.windows(2).all(|w|w[0] + 1 == w[1]));
+		last_page = *pages.last().unwrap();
+	}
+	assert_eq!(last_page, 4);
+}
+
+#[test]
+fn test_flush_on_drop() {
+	let mut sink = Vec::<u8>::new();
+	let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+	writer.write_header_packet(b"header").unwrap();
+	writer.write_all(b"half-written").unwrap();
+	writer.discard();
+	let (packets, consumed) = OggPacket::parse_all(&sink);
+	assert_eq!(consumed, sink.len());
+	assert_eq!(packets.len(), 1);
+	assert!(!packets[0].is_last_page());
+
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		writer.write_all(b"data").unwrap();
+		writer.set_flush_on_drop(false);
+	}
+	assert!(sink.is_empty());
+
+	let mut sink = Vec::<u8>::new();
+	OggStreamWriter::new(&mut sink, 0x12345678).write_all(b"data").unwrap();
+	let (packets, _) = OggPacket::parse_all(&sink);
+	assert!(packets[0].is_last_page());
+	assert_eq!(packets[0].get_inner_data(), b"data");
 }
@@ -0,0 +1,400 @@
+use std::{
+	collections::VecDeque,
+	fmt::Debug,
+	io::{self, ErrorKind, Read},
+};
+
+use crate::{Codec, OggStreamReader, VorbisComments, detect_codec, packet_granules_from_samples};
+
+/// * The channel mapping table of an Opus stream, which is present if the channel mapping family isn't 0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelMapping {
+	/// * The number of Opus streams in each packet
+	pub stream_count: u8,
+
+	/// * How many of the streams are coupled, which decode to two channels. They are the first streams.
+	pub coupled_count: u8,
+
+	/// * The decoded channel of each output channel: the channels of the coupled streams come first, then the mono streams. 255 means a silent channel.
+	pub mapping: Vec<u8>,
+}
+
+/// * The identification header of an Opus stream, which is its BOS packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpusHead {
+	/// * The version, the major version in the high 4 bits must be 0
+	pub version: u8,
+
+	/// * The number of output channels
+	pub channels: u8,
+
+	/// * How many samples at 48 kHz to discard from the beginning of the decoded output
+	pub pre_skip: u16,
+
+	/// * The sample rate of the original input, only informational, the granule position is always at 48 kHz
+	pub input_sample_rate: u32,
+
+	/// * The gain to apply to the decoded output in Q7.8 dB
+	pub output_gain: i16,
+
+	/// * 0: mono/stereo, 1: Vorbis channel order surround, 255: undefined/ambisonics
+	pub channel_mapping_family: u8,
+
+	/// * The channel mapping table, `None` for the channel mapping family 0
+	pub channel_mapping: Option<ChannelMapping>,
+}
+
+impl OpusHead {
+	/// * The size of the header without the channel mapping table
+	pub const SIZE: usize = 19;
+
+	pub fn parse(packet: &[u8]) -> io::Result<Self> {
+		if packet.len() < Self::SIZE {
+			return Err(io::Error::new(ErrorKind::UnexpectedEof, format!("The `OpusHead` packet is too small: {} < {}", packet.len(), Self::SIZE)));
+		}
+		if packet[0..8] != *b"OpusHead" {
+			return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing `OpusHead`: expected `OpusHead`, got `{}`", String::from_utf8_lossy(&packet[0..8]))));
+		}
+		let version = packet[8];
+		if version >> 4 != 0 {
+			return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing `OpusHead`: unsupported `version` = {version}")));
+		}
+		let channels = packet[9];
+		let channel_mapping_family = packet[18];
+		let channel_mapping = if channel_mapping_family == 0 {
+			None
+		} else {
+			Some(Self::parse_channel_mapping(packet, channels)?)
+		};
+		Ok(Self {
+			version,
+			channels,
+			pre_skip: u16::from_le_bytes([packet[10], packet[11]]),
+			input_sample_rate: u32::from_le_bytes(packet[12..16].try_into().unwrap()),
+			output_gain: i16::from_le_bytes([packet[16], packet[17]]),
+			channel_mapping_family,
+			channel_mapping,
+		})
+	}
+
+	/// * Parse the channel mapping table after the fixed fields, the mapping of each channel must refer to a decoded channel or be silent.
+	fn parse_channel_mapping(packet: &[u8], channels: u8) -> io::Result<ChannelMapping> {
+		let table_size = 2 + channels as usize;
+		let table = packet.get(Self::SIZE..Self::SIZE + table_size).ok_or_else(||io::Error::new(ErrorKind::UnexpectedEof, format!("The `OpusHead` packet is too small for the channel mapping table of {channels} channels: {} < {}", packet.len(), Self::SIZE + table_size)))?;
+		let stream_count = table[0];
+		let coupled_count = table[1];
+		if stream_count == 0 || coupled_count > stream_count || stream_count as usize + coupled_count as usize > 255 {
+			return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing `OpusHead`: invalid stream count {stream_count} with coupled stream count {coupled_count}")));
+		}
+		let num_decoded = stream_count as usize + coupled_count as usize;
+		let mapping = table[2..].to_vec();
+		if let Some(bad) = mapping.iter().find(|&&m|m != 255 && m as usize >= num_decoded) {
+			return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing `OpusHead`: the channel mapping {bad} exceeds the {num_decoded} decoded channels")));
+		}
+		Ok(ChannelMapping {
+			stream_count,
+			coupled_count,
+			mapping,
+		})
+	}
+}
+
+/// * Converts the granule positions of an Opus stream to the playable sample positions, considering the pre-skip.
+/// * The granule position counts the samples at 48 kHz including the pre-skip samples, which must be discarded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpusTimeline {
+	pub pre_skip: u16,
+}
+
+impl OpusTimeline {
+	pub fn new(head: &OpusHead) -> Self {
+		Self {
+			pre_skip: head.pre_skip,
+		}
+	}
+
+	/// * The playable sample index at 48 kHz of the granule position, the positions during the pre-skip are clamped to 0.
+	pub fn playable_position(&self, granule_position: u64) -> u64 {
+		granule_position.saturating_sub(self.pre_skip as u64)
+	}
+
+	/// * If the granule position is still in the pre-skip part, which has no playable samples yet.
+	pub fn is_in_pre_skip(&self, granule_position: u64) -> bool {
+		granule_position <= self.pre_skip as u64
+	}
+
+	/// * The total playable samples at 48 kHz, given the granule position of the last packet.
+	pub fn total_samples(&self, final_granule_position: u64) -> u64 {
+		self.playable_position(final_granule_position)
+	}
+
+	/// * The playable time in seconds of the granule position
+	pub fn playable_seconds(&self, granule_position: u64) -> f64 {
+		self.playable_position(granule_position) as f64 / 48000.0
+	}
+}
+
+/// * Get the number of samples at 48 kHz of an Opus packet from its TOC byte, returns `None` for an empty or malformed packet.
+pub fn opus_packet_samples(packet: &[u8]) -> Option<u64> {
+	let toc = *packet.first()?;
+	let config = toc >> 3;
+	let frame_size = match config {
+		0..=11 => [480, 960, 1920, 2880][config as usize % 4],
+		12..=15 => [480, 960][config as usize % 2],
+		_ => [120, 240, 480, 960][config as usize % 4],
+	};
+	let num_frames = match toc & 3 {
+		0 => 1,
+		1 | 2 => 2,
+		_ => (*packet.get(1)? & 0x3F) as u64,
+	};
+	Some(frame_size * num_frames)
+}
+
+/// * An audio packet of an Opus stream, ready for the decoder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpusAudioPacket {
+	/// * The data of the packet
+	pub data: Vec<u8>,
+
+	/// * The granule position at the end of the packet, at 48 kHz including the pre-skip
+	pub granule_position: u64,
+
+	/// * The playable sample position at 48 kHz at the end of the packet, which is the granule position minus the pre-skip
+	pub playable_position: u64,
+}
+
+/// * A reader of an Opus file, which reads the `OpusHead` and `OpusTags` headers, then gives the audio packets with their granule positions.
+/// * The granule position of each packet is calculated from the sample counts of the packets, counting backwards from the granule position of the page where they end.
+///   For the EOS packet, the granule positions count forwards from the previous page and are clamped to the granule position of the page, which trims the end of the stream.
+/// * If the file is multiplexed, only the first Opus stream is read, the packets of the other streams are skipped.
+pub struct OpusFile<R>
+where
+	R: Read + Debug {
+	/// * The identification header
+	pub head: OpusHead,
+
+	/// * The comment header
+	pub tags: VorbisComments,
+
+	/// * The stream ID of the Opus stream
+	pub stream_id: u32,
+
+	/// * Converts the granule positions to the playable sample positions
+	pub timeline: OpusTimeline,
+
+	reader: OggStreamReader<R>,
+
+	/// * The packets which are waiting for the granule position of the page where they end
+	waiting: Vec<Vec<u8>>,
+
+	/// * The packets with the granule positions calculated, to be returned by `get_packet()`
+	ready: VecDeque<OpusAudioPacket>,
+
+	/// * The granule position of the last page with a granule position
+	last_granule: u64,
+
+	/// * If the EOS packet of the Opus stream was read
+	finished: bool,
+}
+
+impl<R> OpusFile<R>
+where
+	R: Read + Debug {
+	/// * Open an Opus file, the first stream must be an Opus stream.
+	pub fn open(reader: R) -> io::Result<Self> {
+		let mut reader = OggStreamReader::new(reader);
+		let no_headers = ||io::Error::new(ErrorKind::UnexpectedEof, "The Opus headers were not found before the end of the stream");
+		let first = reader.get_logical_packet()?.ok_or_else(no_headers)?;
+		if !matches!(detect_codec(&first.data), Codec::Opus{..}) {
+			return Err(io::Error::new(ErrorKind::InvalidData, "The first stream is not an Opus stream"));
+		}
+		let head = OpusHead::parse(&first.data)?;
+		let tags = loop {
+			reader.e_o_s = false;
+			let packet = reader.get_logical_packet()?.ok_or_else(no_headers)?;
+			if packet.stream_id == first.stream_id {
+				break VorbisComments::parse_opus_tags(&packet.data)?;
+			}
+		};
+		Ok(Self {
+			timeline: OpusTimeline::new(&head),
+			head,
+			tags,
+			stream_id: first.stream_id,
+			reader,
+			waiting: Vec::new(),
+			ready: VecDeque::new(),
+			last_granule: 0,
+			finished: false,
+		})
+	}
+
+	/// * Calculate the granule positions of the waiting packets which end in a page with `granule_position`.
+	fn resolve(&mut self, granule_position: u64, is_end_of_stream: bool) {
+		let samples: Vec<u64> = self.waiting.iter().map(|p|opus_packet_samples(p).unwrap_or(0)).collect();
+		let granules = if is_end_of_stream {
+			let mut granule = self.last_granule;
+			samples.iter().map(|&s|{
+				granule += s;
+				granule.min(granule_position)
+			}).collect()
+		} else {
+			packet_granules_from_samples(granule_position, &samples)
+		};
+		for (data, granule_position) in self.waiting.drain(..).zip(granules) {
+			self.ready.push_back(OpusAudioPacket {
+				data,
+				granule_position,
+				playable_position: self.timeline.playable_position(granule_position),
+			});
+		}
+		self.last_granule = granule_position;
+	}
+
+	/// * Get the next audio packet, returns `Ok(None)` at the end of the Opus stream.
+	pub fn get_packet(&mut self) -> io::Result<Option<OpusAudioPacket>> {
+		while self.ready.is_empty() && !self.finished {
+			// The EOS packet of another stream doesn't end the input.
+			self.reader.e_o_s = false;
+			match self.reader.get_logical_packet()? {
+				Some(packet) if packet.stream_id == self.stream_id => {
+					self.waiting.push(packet.data);
+					self.finished = packet.is_last_packet;
+					if packet.granule_position != u64::MAX {
+						self.resolve(packet.granule_position, packet.is_last_packet);
+					}
+				}
+				Some(_) => (),
+				None => self.finished = true,
+			}
+		}
+		if self.ready.is_empty() && !self.waiting.is_empty() {
+			// The stream ended without the granule position of the last packets, count them forwards.
+			let samples: u64 = self.waiting.iter().map(|p|opus_packet_samples(p).unwrap_or(0)).sum();
+			self.resolve(self.last_granule + samples, true);
+		}
+		Ok(self.ready.pop_front())
+	}
+
+	/// * Get back the underlying reader.
+	pub fn into_inner(self) -> OggStreamReader<R> {
+		self.reader
+	}
+}
+
+impl<R> Iterator for OpusFile<R>
+where
+	R: Read + Debug {
+	type Item = io::Result<OpusAudioPacket>;
+	fn next(&mut self) -> Option<Self::Item> {
+		self.get_packet().transpose()
+	}
+}
+
+impl<R> Debug for OpusFile<R>
+where
+	R: Read + Debug {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.debug_struct("OpusFile")
+		.field("head", &self.head)
+		.field("tags", &self.tags)
+		.field("stream_id", &self.stream_id)
+		.field("reader", &self.reader.reader)
+		.field("waiting", &self.waiting.len())
+		.field("ready", &self.ready.len())
+		.field("last_granule", &self.last_granule)
+		.field("finished", &self.finished)
+		.finish()
+	}
+}
+
+#[test]
+fn test_opus_timeline() {
+	let head = OpusHead::parse(b"OpusHead\x01\x02\x00\x0f\x80\xbb\0\0\0\0\0").unwrap();
+	assert_eq!(head.channels, 2);
+	assert_eq!(head.pre_skip, 3840);
+	assert_eq!(head.input_sample_rate, 48000);
+	let timeline = OpusTimeline::new(&head);
+	// The pages of 20ms packets
+	let granules = [960u64, 1920, 2880, 3840, 4800, 5760];
+	let positions: Vec<u64> = granules.iter().map(|&g|timeline.playable_position(g)).collect();
+	assert_eq!(positions, [0, 0, 0, 0, 960, 1920]);
+	assert!(timeline.is_in_pre_skip(3840));
+	assert!(!timeline.is_in_pre_skip(4800));
+	assert_eq!(timeline.playable_seconds(48000 + 3840), 1.0);
+}
+
+#[test]
+fn test_opus_file() {
+	use std::io::Cursor;
+	use crate::{OggPacket, OggPacketType, mux_pages};
+	let head = b"OpusHead\x01\x02\x38\x01\x80\xbb\0\0\0\0\0";
+	let mut tags = b"OpusTags".to_vec();
+	tags.extend(7u32.to_le_bytes());
+	tags.extend(b"tinyogg");
+	tags.extend(1u32.to_le_bytes());
+	tags.extend(10u32.to_le_bytes());
+	tags.extend(b"TITLE=Song");
+	// A 20 ms CELT packet, and a 30 ms packet of three 10 ms CELT frames
+	let frame_20ms = [31 << 3, 0x55];
+	let frames_30ms = [(18 << 3) | 3, 3, 0x55];
+	assert_eq!(opus_packet_samples(&frame_20ms), Some(960));
+	assert_eq!(opus_packet_samples(&frames_30ms), Some(1440));
+	assert_eq!(opus_packet_samples(&[]), None);
+
+	let page = |packet_type, packet_index, granule_position, packets: &[&[u8]]| {
+		let mut page = OggPacket::new(0x12345678, packet_type, packet_index);
+		page.granule_position = granule_position;
+		for packet in packets {
+			page.write(packet);
+		}
+		page
+	};
+	let bytes = mux_pages([
+		page(OggPacketType::BeginOfStream, 0, 0, &[head]),
+		page(OggPacketType::Continuation, 1, 0, &[&tags]),
+		page(OggPacketType::Continuation, 2, 312 + 2880, &[&frame_20ms, &frame_20ms, &frame_20ms]),
+		// The last 100 samples are trimmed
+		page(OggPacketType::EndOfStream, 3, 312 + 2880 + 2400 - 100, &[&frames_30ms, &frame_20ms]),
+	]);
+	let mut opus = OpusFile::open(Cursor::new(bytes)).unwrap();
+	assert_eq!(opus.head.pre_skip, 312);
+	assert_eq!(opus.tags.vendor, "tinyogg");
+	assert_eq!(opus.tags.comments, [("TITLE".to_string(), "Song".to_string())]);
+	let packets: Vec<OpusAudioPacket> = opus.by_ref().map(|p|p.unwrap()).collect();
+	let granules: Vec<u64> = packets.iter().map(|p|p.granule_position).collect();
+	assert_eq!(granules, [312 + 960, 312 + 1920, 312 + 2880, 312 + 4320, 312 + 5180]);
+	let positions: Vec<u64> = packets.iter().map(|p|p.playable_position).collect();
+	assert_eq!(positions, [960, 1920, 2880, 4320, 5180]);
+	assert_eq!(packets[3].data, frames_30ms);
+	assert!(opus.get_packet().unwrap().is_none());
+
+	let e = OpusFile::open(std::fs::File::open("test.ogg").unwrap()).unwrap_err();
+	assert_eq!(e.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_opus_channel_mapping() {
+	let head = OpusHead::parse(b"OpusHead\x01\x02\x38\x01\x80\xbb\0\0\0\0\0").unwrap();
+	assert_eq!(head.channel_mapping, None);
+
+	// 5.1 surround: 4 streams, 2 of them coupled
+	let mut surround = b"OpusHead\x01\x06\x38\x01\x80\xbb\0\0\0\0\x01".to_vec();
+	surround.extend([4, 2, 0, 4, 1, 2, 3, 5]);
+	let head = OpusHead::parse(&surround).unwrap();
+	assert_eq!(head.channel_mapping, Some(ChannelMapping{stream_count: 4, coupled_count: 2, mapping: vec![0, 4, 1, 2, 3, 5]}));
+	assert!(OpusHead::parse(&surround[..surround.len() - 1]).is_err());
+	let mut bad_mapping = surround.clone();
+	bad_mapping[OpusHead::SIZE + 2] = 6;
+	assert_eq!(OpusHead::parse(&bad_mapping).unwrap_err().kind(), ErrorKind::InvalidData);
+	let mut bad_counts = surround.clone();
+	bad_counts[OpusHead::SIZE + 1] = 5;
+	assert_eq!(OpusHead::parse(&bad_counts).unwrap_err().kind(), ErrorKind::InvalidData);
+
+	// First order ambisonics with a silent channel
+	let mut ambisonics = b"OpusHead\x01\x04\x38\x01\x80\xbb\0\0\0\0\xff".to_vec();
+	ambisonics.extend([4, 0, 0, 1, 2, 255]);
+	let head = OpusHead::parse(&ambisonics).unwrap();
+	assert_eq!(head.channel_mapping.unwrap().mapping, [0, 1, 2, 255]);
+}
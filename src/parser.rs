@@ -0,0 +1,177 @@
+use std::io;
+
+use crate::{OggError, OggPacket};
+
+/// * A sans-I/O parser: the caller feeds the bytes as they arrive with `push_bytes()`, and takes the parsed packets with `next_page()`.
+/// * It doesn't depend on `Read`, so it works over channels, custom transports, WASM, or any async runtime.
+/// * A packet with a wrong checksum is skipped after the error is returned. On the other errors, e.g. a bad capture pattern, the parser skips to the next `OggS` capture pattern.
+#[derive(Debug, Clone)]
+pub struct OggParser {
+	/// * The bytes fed by the caller
+	buffer: Vec<u8>,
+
+	/// * How many bytes at the beginning of `buffer` were consumed, they are discarded at the next `push_bytes()`
+	offset: usize,
+
+	/// * How many bytes were consumed, it's the byte offset of the next packet in the input
+	position: u64,
+
+	/// * The capture pattern of the packets, `OggS` unless a custom one is given to `with_capture_pattern()`
+	capture_pattern: [u8; 4],
+}
+
+impl OggParser {
+	pub fn new() -> Self {
+		Self::with_capture_pattern(OggPacket::CAPTURE_PATTERN)
+	}
+
+	/// * Create a parser for the containers built on the Ogg framing with a custom capture pattern instead of `OggS`, the other fields and the checksum are the same as Ogg.
+	pub fn with_capture_pattern(capture_pattern: [u8; 4]) -> Self {
+		Self {
+			buffer: Vec::new(),
+			offset: 0,
+			position: 0,
+			capture_pattern,
+		}
+	}
+
+	/// * Feed the bytes which arrived, they don't have to be aligned to the packet boundaries.
+	pub fn push_bytes(&mut self, bytes: &[u8]) {
+		// Only compact the buffer when feeding it, the partial packet at the end is moved to the beginning.
+		self.buffer.drain(..self.offset);
+		self.offset = 0;
+		self.buffer.extend(bytes);
+	}
+
+	fn consume(&mut self, length: usize) {
+		self.offset += length;
+		self.position += length as u64;
+	}
+
+	/// * Parse the next packet from the fed bytes, returns `None` if more bytes are needed.
+	/// * When the input ends, check `buffered_len()` to tell a clean end at a packet boundary from a truncated packet.
+	pub fn next_page(&mut self) -> Option<io::Result<OggPacket>> {
+		let data = &self.buffer[self.offset..];
+		let mut packet_length = 0usize;
+		match OggPacket::parse_with(data, &mut packet_length, false, self.capture_pattern) {
+			Ok(packet) => {
+				self.consume(packet_length);
+				Some(Ok(packet))
+			}
+			Err(OggError::UnexpectedEof{..}) => None,
+			Err(e @ OggError::ChecksumMismatch{..}) => {
+				let e = e.at_offset(self.position);
+				self.consume(packet_length);
+				Some(Err(e.into()))
+			}
+			Err(e) => {
+				// Skip to the next capture pattern, but keep the last 3 bytes which may begin one.
+				let skip = match data[1..].windows(4).position(|w|w == self.capture_pattern) {
+					Some(next) => 1 + next,
+					None => data.len().saturating_sub(3).max(1),
+				};
+				self.consume(skip);
+				Some(Err(e.into()))
+			}
+		}
+	}
+
+	/// * How many of the fed bytes aren't parsed yet
+	pub fn buffered_len(&self) -> usize {
+		self.buffer.len() - self.offset
+	}
+
+	/// * Get the byte offset of the next packet in the input.
+	pub fn stream_position(&self) -> u64 {
+		self.position
+	}
+}
+
+impl Default for OggParser {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[test]
+fn test_parser() {
+	let bytes = std::fs::read("test.ogg").unwrap();
+	let (expected, _) = OggPacket::parse_all(&bytes);
+	let mut parser = OggParser::new();
+	let mut packets = Vec::<OggPacket>::new();
+	for chunk in bytes.chunks(1000) {
+		parser.push_bytes(chunk);
+		while let Some(packet) = parser.next_page() {
+			packets.push(packet.unwrap());
+		}
+	}
+	assert_eq!(packets, expected);
+	assert_eq!(parser.buffered_len(), 0);
+	assert_eq!(parser.stream_position(), bytes.len() as u64);
+
+	// The garbage before a packet and the packet with a wrong checksum are skipped after the errors.
+	let mut parser = OggParser::new();
+	let length = expected[0].total_page_size();
+	let mut corrupt = bytes[..length].to_vec();
+	corrupt[length - 1] ^= 0xFF;
+	parser.push_bytes(b"garbage");
+	parser.push_bytes(&corrupt);
+	parser.push_bytes(&bytes[..length]);
+	let e = parser.next_page().unwrap().unwrap_err();
+	assert_eq!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(&OggError::BadCapturePattern(*b"garb")));
+	let e = parser.next_page().unwrap().unwrap_err();
+	assert!(matches!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(OggError::ChecksumMismatch{offset: 7, ..})));
+	assert_eq!(parser.next_page().unwrap().unwrap(), expected[0]);
+	assert!(parser.next_page().is_none());
+	assert_eq!(parser.stream_position(), (7 + length * 2) as u64);
+
+	parser.push_bytes(&bytes[length..length + 10]);
+	assert!(parser.next_page().is_none());
+	assert_eq!(parser.buffered_len(), 10);
+}
+
+#[test]
+fn test_custom_capture_pattern() {
+	use std::io::{Cursor, Write};
+	use crate::{OggStreamReader, OggStreamWriter};
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = OggStreamWriter::new(&mut sink, 0x12345678);
+		writer.set_capture_pattern(*b"XyzW");
+		writer.write_header_packet(b"header").unwrap();
+		writer.write_all(&[1u8; 70000]).unwrap();
+	}
+	let (packets, _) = OggPacket::parse_all(&sink);
+	assert!(packets.is_empty());
+	let mut packet_length = 0usize;
+	let first = OggPacket::from_bytes_with_capture_pattern(&sink, &mut packet_length, *b"XyzW").unwrap();
+	assert_eq!(first.get_inner_data(), b"header");
+	assert_eq!(first.to_bytes_with_capture_pattern(*b"XyzW"), sink[..packet_length]);
+	assert_eq!(OggPacket::get_checksum(&sink[..packet_length]).unwrap(), first.checksum);
+
+	let mut parser = OggParser::with_capture_pattern(*b"XyzW");
+	parser.push_bytes(b"junk");
+	parser.push_bytes(&sink);
+	assert!(parser.next_page().unwrap().is_err());
+	let mut pages = Vec::<OggPacket>::new();
+	while let Some(page) = parser.next_page() {
+		pages.push(page.unwrap());
+	}
+	assert_eq!(pages.len(), 3);
+	assert_eq!(pages[0], first);
+	assert!(pages[2].is_last_page());
+
+	let mut oggreader = OggStreamReader::new(Cursor::new(sink.clone()));
+	oggreader.set_capture_pattern(*b"XyzW");
+	let mut num_pages = 0usize;
+	while oggreader.get_packet().unwrap().is_some() {
+		num_pages += 1;
+	}
+	assert_eq!(num_pages, 3);
+
+	// An Ogg parser rejects the custom capture pattern
+	let mut parser = OggParser::new();
+	parser.push_bytes(&sink);
+	let e = parser.next_page().unwrap().unwrap_err();
+	assert_eq!(e.get_ref().unwrap().downcast_ref::<OggError>(), Some(&OggError::BadCapturePattern(*b"XyzW")));
+}
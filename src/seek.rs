@@ -0,0 +1,230 @@
+use std::{
+	fmt::Debug,
+	io::{self, ErrorKind, Read, Seek, SeekFrom},
+};
+
+use crate::{OggError, OggPacket, OggStreamReader};
+
+/// * The granule positions of the packets of a stream along with their byte offsets, for repeated seeking without bisecting every time
+/// * It costs 16 bytes per packet, e.g. about 1.6 MB for 100000 packets.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SeekIndex {
+	/// * The `(granule_position, byte_offset)` of each packet in the order of the stream. The packets with the granule position -1 are not recorded.
+	pub entries: Vec<(u64, u64)>,
+}
+
+impl SeekIndex {
+	/// * Get the byte offset of the last packet with a granule position at or before `target`.
+	/// * If every packet is after `target`, the offset of the first packet is returned. For an empty index, returns 0.
+	pub fn byte_for_granule(&self, target: u64) -> u64 {
+		let index = self.entries.partition_point(|&(granule, _)|granule <= target);
+		match index {
+			0 => self.entries.first().map_or(0, |&(_, offset)|offset),
+			i => self.entries[i - 1].1,
+		}
+	}
+}
+
+/// * Read all of the packets in a single forward pass, and record the granule positions and the byte offsets of the packets of the stream.
+/// * The offsets count from where the reader was when it was given to the `OggStreamReader`.
+pub fn build_seek_index<R>(reader: &mut OggStreamReader<R>, stream_id: u32) -> io::Result<SeekIndex>
+where
+	R: Read + Debug {
+	let mut index = SeekIndex::default();
+	while let Some((offset, packet)) = reader.get_packet_with_offset()? {
+		if packet.stream_id == stream_id && packet.granule_position != u64::MAX {
+			index.entries.push((packet.granule_position, offset));
+		}
+	}
+	Ok(index)
+}
+
+/// * A reader for seekable inputs, which resyncs to the next packet boundary after seeking, so the parse state is never corrupted by a seek into the middle of a packet.
+/// * After `seek()`, the next `get_packet()` returns the first complete and valid packet at or after the offset, the partial packet at the offset is discarded.
+/// * The offsets are the positions in the underlying reader, the `stream_position()` of the inner `OggStreamReader` also counts from the beginning of the underlying reader.
+/// * The resync finds the packets as the inner reader parses them, with its capture pattern and its version setting, see `OggStreamReader::set_capture_pattern()`.
+pub struct OggSeekReader<R>
+where
+	R: Read + Seek + Debug {
+	/// * The inner reader, use it to read the packets or the logical packets
+	pub reader: OggStreamReader<R>,
+}
+
+impl<R> OggSeekReader<R>
+where
+	R: Read + Seek + Debug {
+	const SCAN_SIZE: usize = 4096;
+
+	pub fn new(mut reader: R) -> io::Result<Self> {
+		let position = reader.stream_position()?;
+		let mut reader = OggStreamReader::new(reader);
+		reader.discard_buffered(position);
+		Ok(Self {
+			reader,
+		})
+	}
+
+	pub fn get_packet(&mut self) -> io::Result<Option<OggPacket>> {
+		self.reader.get_packet()
+	}
+
+	/// * Seek to the packet at or before the granule position `target` in the index built by `build_seek_index()`, returns the offset of the packet.
+	/// * The index must be built from the same reader starting at offset 0.
+	pub fn seek_granule(&mut self, index: &SeekIndex, target: u64) -> io::Result<u64> {
+		self.seek(SeekFrom::Start(index.byte_for_granule(target)))
+	}
+
+	/// * Find the first valid packet from `start` of the underlying reader and position the reader to it, returns its offset.
+	/// * If there isn't any, the reader is left at the end, and the offset of the end is returned.
+	fn resync(&mut self, start: u64) -> io::Result<u64> {
+		let capture_pattern = self.reader.capture_pattern;
+		let any_version = self.reader.accept_any_version && !self.reader.strict;
+		let reader = &mut self.reader.reader;
+		let mut buf = Vec::<u8>::new();
+		let mut base = start;
+		let mut searched = 0usize;
+		let mut e_o_f = false;
+		loop {
+			let mut pending = None;
+			while let Some(found) = buf[searched..].windows(4).position(|w|w == capture_pattern) {
+				let candidate = searched + found;
+				match OggPacket::parse_with(&buf[candidate..], &mut 0, any_version, capture_pattern) {
+					Ok(_) => {
+						let offset = base + candidate as u64;
+						reader.seek(SeekFrom::Start(offset))?;
+						return Ok(offset);
+					}
+					Err(OggError::UnexpectedEof{..}) if !e_o_f => {
+						pending = Some(candidate);
+						break;
+					}
+					Err(_) => searched = candidate + 1,
+				}
+			}
+			if e_o_f {
+				return Ok(base + buf.len() as u64);
+			}
+			// Discard the bytes without a packet, but keep the last 3 bytes which may begin a capture pattern.
+			let keep_from = pending.unwrap_or(searched.max(buf.len().saturating_sub(3)));
+			buf.drain(..keep_from);
+			base += keep_from as u64;
+			searched -= keep_from.min(searched);
+			let mut chunk = vec![0u8; Self::SCAN_SIZE];
+			let size = loop {
+				match reader.read(&mut chunk) {
+					Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+					result => break result?,
+				}
+			};
+			e_o_f = size == 0;
+			buf.extend(&chunk[..size]);
+		}
+	}
+}
+
+/// * Seeking repositions the underlying reader, then resyncs to the first valid packet at or after the position, and returns the offset of it.
+/// * `SeekFrom::Current` is relative to `stream_position()` of the inner reader, which is the offset of the next packet, not the position of the underlying reader.
+impl<R> Seek for OggSeekReader<R>
+where
+	R: Read + Seek + Debug {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let pos = match pos {
+			SeekFrom::Current(delta) => match self.reader.stream_position().checked_add_signed(delta) {
+				Some(offset) => SeekFrom::Start(offset),
+				None => return Err(io::Error::new(ErrorKind::InvalidInput, "Seeking to a negative or overflowing position")),
+			}
+			pos => pos,
+		};
+		let start = self.reader.reader.seek(pos)?;
+		let offset = self.resync(start)?;
+		self.reader.discard_buffered(offset);
+		Ok(offset)
+	}
+}
+
+impl<R> Debug for OggSeekReader<R>
+where
+	R: Read + Seek + Debug {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.debug_struct("OggSeekReader")
+		.field("reader", &self.reader.reader)
+		.field("stream_position", &self.reader.stream_position())
+		.finish()
+	}
+}
+
+#[test]
+fn test_seek_index() {
+	use std::io::{Cursor, Write};
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = crate::OggStreamWriter::new(&mut sink, 0x12345678);
+		for i in 0..10u64 {
+			writer.write_all(&[0u8; 100]).unwrap();
+			writer.flush_page(i * 1000).unwrap();
+		}
+	}
+	let mut oggreader = OggStreamReader::new(Cursor::new(sink));
+	let index = build_seek_index(&mut oggreader, 0x12345678).unwrap();
+	assert_eq!(index.entries.len(), 11);
+	let page_size = 27 + 1 + 100;
+	assert_eq!(index.byte_for_granule(0), 0);
+	assert_eq!(index.byte_for_granule(2500), 2 * page_size);
+	assert_eq!(index.byte_for_granule(3000), 3 * page_size);
+	assert_eq!(index.byte_for_granule(u64::MAX - 1), 10 * page_size);
+	assert_eq!(SeekIndex::default().byte_for_granule(1000), 0);
+}
+
+#[test]
+fn test_seek_reader() {
+	use std::io::{Cursor, Write};
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = crate::OggStreamWriter::new(&mut sink, 0x12345678);
+		for i in 0..100u64 {
+			writer.write_all(&[b'O', b'g', b'g', b'S', i as u8]).unwrap();
+			writer.write_packet(&vec![i as u8; 1000 + i as usize * 100]).unwrap();
+			writer.flush_page(i * 1000).unwrap();
+		}
+	}
+	let offsets: Vec<u64> = {
+		let mut oggreader = OggStreamReader::new(Cursor::new(&sink));
+		std::iter::from_fn(||oggreader.get_packet_with_offset().unwrap().map(|(offset, _)|offset)).collect()
+	};
+	let mut seeker = OggSeekReader::new(Cursor::new(sink.clone())).unwrap();
+	for target in [0, 1, offsets[10] - 1, offsets[10], offsets[10] + 30, offsets[50] + 3] {
+		let expected = *offsets.iter().find(|&&o|o >= target).unwrap();
+		assert_eq!(seeker.seek(SeekFrom::Start(target)).unwrap(), expected);
+		let (offset, packet) = seeker.reader.get_packet_with_offset().unwrap().unwrap();
+		assert_eq!(offset, expected);
+		assert_eq!(packet.packet_index as usize, offsets.iter().position(|&o|o == expected).unwrap());
+	}
+	assert_eq!(seeker.seek(SeekFrom::Current(-1)).unwrap(), offsets[52]);
+	assert_eq!(seeker.seek(SeekFrom::End(-10)).unwrap(), sink.len() as u64);
+	assert!(seeker.get_packet().unwrap().is_none());
+
+	let index = build_seek_index(&mut OggStreamReader::new(Cursor::new(&sink)), 0x12345678).unwrap();
+	assert_eq!(seeker.seek_granule(&index, 20500).unwrap(), offsets[20]);
+	assert_eq!(seeker.get_packet().unwrap().unwrap().granule_position, 20000);
+}
+
+#[test]
+fn test_seek_custom_capture_pattern() {
+	use std::io::{Cursor, Write};
+	let mut sink = Vec::<u8>::new();
+	{
+		let mut writer = crate::OggStreamWriter::new(&mut sink, 0x12345678);
+		writer.set_capture_pattern(*b"XyzW");
+		for i in 0..10u64 {
+			writer.write_all(&[i as u8; 100]).unwrap();
+			writer.flush_page(i * 1000).unwrap();
+		}
+	}
+	let mut seeker = OggSeekReader::new(Cursor::new(sink)).unwrap();
+	seeker.reader.set_capture_pattern(*b"XyzW");
+	let page_size = 27 + 1 + 100;
+	assert_eq!(seeker.seek(SeekFrom::Start(page_size + 1)).unwrap(), 2 * page_size);
+	let packet = seeker.get_packet().unwrap().unwrap();
+	assert_eq!(packet.packet_index, 2);
+	assert_eq!(packet.granule_position, 2000);
+}
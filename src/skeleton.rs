@@ -0,0 +1,162 @@
+use std::io::{self, ErrorKind};
+
+fn check_packet(packet: &[u8], signature: &[u8; 8], min_size: usize) -> io::Result<()> {
+	if packet.len() < min_size {
+		Err(io::Error::new(ErrorKind::UnexpectedEof, format!("The Skeleton `{}` packet is too small: {} < {min_size}", String::from_utf8_lossy(&signature[..7]), packet.len())))
+	} else if packet[0..8] != *signature {
+		Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing Skeleton packet: expected `{}`, got `{}`", String::from_utf8_lossy(signature), String::from_utf8_lossy(&packet[0..8]))))
+	} else {
+		Ok(())
+	}
+}
+
+fn read_i64(packet: &[u8], offset: usize) -> i64 {
+	i64::from_le_bytes(packet[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_u32(packet: &[u8], offset: usize) -> u32 {
+	u32::from_le_bytes(packet[offset..offset + 4].try_into().unwrap())
+}
+
+/// * The `fishead` packet, the BOS packet of the Skeleton stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkeletonFishead {
+	pub version_major: u16,
+	pub version_minor: u16,
+
+	/// * The presentation time of the first sample, as a rational number
+	pub presentation_time_numerator: i64,
+	pub presentation_time_denominator: i64,
+
+	/// * The base time, which is the time of granule position 0, as a rational number
+	pub base_time_numerator: i64,
+	pub base_time_denominator: i64,
+
+	/// * The UTC time of the base time, or all zero if not used
+	pub utc: [u8; 20],
+
+	/// * Skeleton 4.0 only: the length of the whole segment in bytes
+	pub segment_length: Option<u64>,
+
+	/// * Skeleton 4.0 only: the byte offset of the first non-header page
+	pub content_byte_offset: Option<u64>,
+}
+
+impl SkeletonFishead {
+	/// * The size of the `fishead` packet of Skeleton 3.0, Skeleton 4.0 adds 16 bytes
+	pub const SIZE: usize = 64;
+
+	pub fn parse(packet: &[u8]) -> io::Result<Self> {
+		check_packet(packet, b"fishead\0", Self::SIZE)?;
+		let version_major = u16::from_le_bytes([packet[8], packet[9]]);
+		let (segment_length, content_byte_offset) = if version_major >= 4 && packet.len() >= Self::SIZE + 16 {
+			(
+				Some(u64::from_le_bytes(packet[64..72].try_into().unwrap())),
+				Some(u64::from_le_bytes(packet[72..80].try_into().unwrap())),
+			)
+		} else {
+			(None, None)
+		};
+		Ok(Self {
+			version_major,
+			version_minor: u16::from_le_bytes([packet[10], packet[11]]),
+			presentation_time_numerator: read_i64(packet, 12),
+			presentation_time_denominator: read_i64(packet, 20),
+			base_time_numerator: read_i64(packet, 28),
+			base_time_denominator: read_i64(packet, 36),
+			utc: packet[44..64].try_into().unwrap(),
+			segment_length,
+			content_byte_offset,
+		})
+	}
+}
+
+/// * The `fisbone` packet, which describes one of the other logical streams
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkeletonFisbone {
+	/// * The `stream_id` of the described stream
+	pub serial_number: u32,
+
+	/// * How many header packets the described stream has
+	pub num_header_packets: u32,
+
+	/// * How many granules per second, as a rational number
+	pub granule_rate_numerator: i64,
+	pub granule_rate_denominator: i64,
+
+	/// * The granule position of the first data packet
+	pub start_granule: i64,
+
+	/// * How many packets to decode before the seek target to get a correct output
+	pub preroll: u32,
+
+	/// * How many low bits of the granule position are used for the offset from the last keyframe, e.g. Theora
+	pub granule_shift: u8,
+
+	/// * The message header fields, e.g. `("Content-Type", "audio/vorbis")`
+	pub message_headers: Vec<(String, String)>,
+}
+
+impl SkeletonFisbone {
+	/// * The size of the fixed fields of the `fisbone` packet
+	pub const SIZE: usize = 52;
+
+	pub fn parse(packet: &[u8]) -> io::Result<Self> {
+		check_packet(packet, b"fisbone\0", Self::SIZE)?;
+		let headers_offset = (read_u32(packet, 8) as usize).saturating_add(8);
+		let message_headers = String::from_utf8_lossy(packet.get(headers_offset..).unwrap_or_default())
+			.split("\r\n")
+			.filter_map(|line|line.split_once(':'))
+			.map(|(name, value)|(name.trim().to_string(), value.trim().to_string()))
+			.collect();
+		Ok(Self {
+			serial_number: read_u32(packet, 12),
+			num_header_packets: read_u32(packet, 16),
+			granule_rate_numerator: read_i64(packet, 20),
+			granule_rate_denominator: read_i64(packet, 28),
+			start_granule: read_i64(packet, 36),
+			preroll: read_u32(packet, 44),
+			granule_shift: packet[48],
+			message_headers,
+		})
+	}
+}
+
+#[test]
+fn test_skeleton() {
+	let mut fishead = b"fishead\0".to_vec();
+	fishead.extend(4u16.to_le_bytes());
+	fishead.extend(0u16.to_le_bytes());
+	for value in [0i64, 1000, 0, 1000] {
+		fishead.extend(value.to_le_bytes());
+	}
+	fishead.extend([0u8; 20]);
+	fishead.extend(12345u64.to_le_bytes());
+	fishead.extend(678u64.to_le_bytes());
+	let fishead = SkeletonFishead::parse(&fishead).unwrap();
+	assert_eq!(fishead.version_major, 4);
+	assert_eq!(fishead.presentation_time_denominator, 1000);
+	assert_eq!(fishead.segment_length, Some(12345));
+	assert_eq!(fishead.content_byte_offset, Some(678));
+
+	let mut fisbone = b"fisbone\0".to_vec();
+	fisbone.extend(44u32.to_le_bytes());
+	fisbone.extend(0x12345678u32.to_le_bytes());
+	fisbone.extend(3u32.to_le_bytes());
+	for value in [44100i64, 1, 0] {
+		fisbone.extend(value.to_le_bytes());
+	}
+	fisbone.extend(2u32.to_le_bytes());
+	fisbone.extend([0u8, 0, 0, 0]);
+	fisbone.extend(b"Content-Type: audio/vorbis\r\nRole: audio/main\r\n");
+	let fisbone = SkeletonFisbone::parse(&fisbone).unwrap();
+	assert_eq!(fisbone.serial_number, 0x12345678);
+	assert_eq!(fisbone.num_header_packets, 3);
+	assert_eq!(fisbone.granule_rate_numerator, 44100);
+	assert_eq!(fisbone.preroll, 2);
+	assert_eq!(fisbone.granule_shift, 0);
+	assert_eq!(fisbone.message_headers[0], ("Content-Type".to_string(), "audio/vorbis".to_string()));
+	assert_eq!(fisbone.message_headers.len(), 2);
+
+	assert!(SkeletonFisbone::parse(b"fishead\0").is_err());
+}
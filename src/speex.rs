@@ -0,0 +1,98 @@
+use std::io::{self, ErrorKind};
+
+/// * The header packet of a Speex stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpeexHeader {
+	/// * The version string of the encoder, e.g. `1.2.1`
+	pub version: String,
+
+	/// * The version ID of the header
+	pub version_id: i32,
+
+	/// * The size of the header, should be 80
+	pub header_size: i32,
+
+	/// * The sample rate in Hz
+	pub rate: i32,
+
+	/// * The mode: 0 for narrowband, 1 for wideband, 2 for ultra-wideband
+	pub mode: i32,
+
+	/// * The version of the mode bitstream
+	pub mode_bitstream_version: i32,
+
+	/// * The number of channels
+	pub channels: i32,
+
+	/// * The bitrate, -1 means unknown
+	pub bitrate: i32,
+
+	/// * The size of a frame in samples
+	pub frame_size: i32,
+
+	/// * If the stream uses variable bitrate
+	pub vbr: bool,
+
+	/// * How many frames are in a packet
+	pub frames_per_packet: i32,
+
+	/// * How many extra header packets follow the comment packet
+	pub extra_headers: i32,
+}
+
+impl SpeexHeader {
+	/// * The size of the header packet
+	pub const SIZE: usize = 80;
+
+	pub fn parse(packet: &[u8]) -> io::Result<Self> {
+		if packet.len() < Self::SIZE {
+			return Err(io::Error::new(ErrorKind::UnexpectedEof, format!("The Speex header packet is too small: {} < {}", packet.len(), Self::SIZE)));
+		}
+		if packet[0..8] != *b"Speex   " {
+			return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing Speex header: expected `Speex   `, got `{}`", String::from_utf8_lossy(&packet[0..8]))));
+		}
+		let read_i32 = |offset: usize|i32::from_le_bytes(packet[offset..offset + 4].try_into().unwrap());
+		let header_size = read_i32(32);
+		if header_size != Self::SIZE as i32 {
+			return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing Speex header: invalid `header_size` = {header_size} (should be {})", Self::SIZE)));
+		}
+		let version = &packet[8..28];
+		let version_len = version.iter().position(|&b|b == 0).unwrap_or(version.len());
+		Ok(Self {
+			version: String::from_utf8_lossy(&version[..version_len]).to_string(),
+			version_id: read_i32(28),
+			header_size,
+			rate: read_i32(36),
+			mode: read_i32(40),
+			mode_bitstream_version: read_i32(44),
+			channels: read_i32(48),
+			bitrate: read_i32(52),
+			frame_size: read_i32(56),
+			vbr: read_i32(60) != 0,
+			frames_per_packet: read_i32(64),
+			extra_headers: read_i32(68),
+		})
+	}
+}
+
+#[test]
+fn test_speex_header() {
+	let mut packet = b"Speex   1.2.1".to_vec();
+	packet.resize(28, 0);
+	for value in [1i32, 80, 16000, 1, 4, 1, -1, 320, 0, 1, 0, 0, 0] {
+		packet.extend(value.to_le_bytes());
+	}
+	let header = SpeexHeader::parse(&packet).unwrap();
+	assert_eq!(header.version, "1.2.1");
+	assert_eq!(header.rate, 16000);
+	assert_eq!(header.mode, 1);
+	assert_eq!(header.channels, 1);
+	assert_eq!(header.bitrate, -1);
+	assert_eq!(header.frame_size, 320);
+	assert!(!header.vbr);
+	assert_eq!(header.frames_per_packet, 1);
+
+	packet[32] = 81;
+	assert!(SpeexHeader::parse(&packet).is_err());
+	assert!(SpeexHeader::parse(&packet[..79]).is_err());
+}
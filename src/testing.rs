@@ -0,0 +1,98 @@
+use crate::{OggPacket, PacketBuilder, mux_pages};
+
+/// * Generate the length of a random logical packet: mostly small, sometimes a multiple of 255 to hit the lacing edge cases, sometimes large enough to span multiple packets.
+fn random_length(rng: &mut impl FnMut() -> u64) -> usize {
+	match rng() % 8 {
+		0 => (rng() % 4) as usize * 255,
+		1 => (rng() % (OggPacket::MAX_PAYLOAD as u64 * 2)) as usize,
+		_ => (rng() % 600) as usize,
+	}
+}
+
+/// * Generate random logical packets for `gen_random_stream()`, the random numbers are drawn from `rng`.
+pub fn gen_random_packets(num_packets: usize, mut rng: impl FnMut() -> u64) -> Vec<Vec<u8>> {
+	(0..num_packets).map(|_| {
+		let length = random_length(&mut rng);
+		(0..length).map(|_|rng() as u8).collect()
+	}).collect()
+}
+
+/// * Generate a valid single-stream Ogg byte buffer with `num_packets` random logical packets, for fuzzing the code that consumes Ogg streams.
+/// * The random numbers are drawn from `rng`, e.g. `|| rng.next_u64()` of the `rand` crate. The stream ID, the packet sizes, the payloads, and the granule positions are random.
+/// * The first packet is the BOS packet, the last packet is the EOS packet, and the large logical packets span multiple packets.
+/// * With `num_packets` being 0, the stream is a single empty BOS and EOS packet.
+pub fn gen_random_stream(num_packets: usize, mut rng: impl FnMut() -> u64) -> Vec<u8> {
+	let stream_id = rng() as u32;
+	let packets = gen_random_packets(num_packets, &mut rng);
+	let mut builder = PacketBuilder::new(stream_id, 0);
+	if packets.is_empty() {
+		return mux_pages(builder.build(&[], 0, true));
+	}
+	let mut granule_position = 0u64;
+	let mut pages = Vec::<OggPacket>::new();
+	for (i, packet) in packets.iter().enumerate() {
+		granule_position += rng() % 4096;
+		pages.extend(builder.build(packet, granule_position, i == packets.len() - 1));
+	}
+	mux_pages(pages)
+}
+
+/// * A SplitMix64 generator for the tests, seeded by `seed`
+#[cfg(test)]
+fn test_rng(mut seed: u64) -> impl FnMut() -> u64 {
+	move || {
+		seed = seed.wrapping_add(0x9E3779B97F4A7C15);
+		let mut z = seed;
+		z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+		z ^ (z >> 31)
+	}
+}
+
+#[test]
+fn test_round_trip_random_packets() {
+	use std::io::Cursor;
+	for seed in 0..64u64 {
+		let mut rng = test_rng(seed);
+		let num_packets = (rng() % 40) as usize;
+		let mut builder = PacketBuilder::new(rng() as u32, 0);
+		let mut pages = Vec::<OggPacket>::new();
+		for (i, packet) in gen_random_packets(num_packets, &mut rng).iter().enumerate() {
+			pages.extend(builder.build(packet, i as u64, i == num_packets - 1));
+		}
+		let bytes = mux_pages(pages.clone());
+		let mut cursor = Cursor::new(bytes.clone());
+		let (parsed, error) = OggPacket::from_cursor(&mut cursor);
+		assert_eq!(error, None, "seed {seed}");
+		assert_eq!(cursor.position() as usize, bytes.len(), "seed {seed}");
+		assert_eq!(parsed.len(), pages.len(), "seed {seed}");
+		for (page, parsed) in pages.iter().zip(parsed.iter()) {
+			assert_eq!(parsed.flags(), page.flags(), "seed {seed}");
+			assert_eq!(parsed.get_inner_data(), page.get_inner_data(), "seed {seed}");
+			assert_eq!(parsed, page, "seed {seed}");
+		}
+		assert_eq!(mux_pages(parsed), bytes, "seed {seed}");
+	}
+}
+
+#[test]
+fn test_gen_random_stream() {
+	use std::io::Cursor;
+	use crate::{OggStreamReader, validate_stream};
+	for seed in 0..16u64 {
+		let num_packets = seed as usize * 3;
+		let bytes = gen_random_stream(num_packets, test_rng(seed));
+		assert_eq!(bytes, gen_random_stream(num_packets, test_rng(seed)));
+		assert!(validate_stream(Cursor::new(&bytes)).is_valid(), "seed {seed}");
+		let (pages, consumed) = OggPacket::parse_all(&bytes);
+		assert_eq!(consumed, bytes.len());
+		assert!(pages[0].is_first_page());
+		assert!(pages.last().unwrap().is_last_page());
+		let mut reader = OggStreamReader::new(Cursor::new(&bytes));
+		let mut num_logical_packets = 0usize;
+		while reader.get_logical_packet().unwrap().is_some() {
+			num_logical_packets += 1;
+		}
+		assert_eq!(num_logical_packets, num_packets.max(1), "seed {seed}");
+	}
+}
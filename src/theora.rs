@@ -0,0 +1,136 @@
+use std::{
+	cmp::Ordering,
+	io::{self, ErrorKind},
+};
+
+/// * The identification header of a Theora stream, which is its BOS packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TheoraIdentHeader {
+	pub version_major: u8,
+	pub version_minor: u8,
+	pub version_revision: u8,
+
+	/// * The size of the coded frame in pixels, which is a multiple of 16
+	pub frame_width: u32,
+	pub frame_height: u32,
+
+	/// * The picture region inside the frame, the offset is counted from the bottom left corner
+	pub picture_width: u32,
+	pub picture_height: u32,
+	pub picture_x: u8,
+	pub picture_y: u8,
+
+	/// * Frames per second, as a rational number
+	pub frame_rate_numerator: u32,
+	pub frame_rate_denominator: u32,
+
+	/// * The pixel aspect ratio, zero means unknown
+	pub aspect_numerator: u32,
+	pub aspect_denominator: u32,
+
+	/// * 0: unspecified, 1: Rec. 470M, 2: Rec. 470BG
+	pub color_space: u8,
+
+	/// * The nominal bitrate in bits per second, zero means unknown
+	pub nominal_bitrate: u32,
+
+	/// * The quality hint, from 0 to 63
+	pub quality: u8,
+
+	/// * How many low bits of the granule position are used for the frames since the last keyframe
+	pub granule_shift: u8,
+
+	/// * 0: 4:2:0, 2: 4:2:2, 3: 4:4:4
+	pub pixel_format: u8,
+}
+
+impl TheoraIdentHeader {
+	/// * The size of the identification header packet
+	pub const SIZE: usize = 42;
+
+	pub fn parse(packet: &[u8]) -> io::Result<Self> {
+		if packet.len() < Self::SIZE {
+			return Err(io::Error::new(ErrorKind::UnexpectedEof, format!("The Theora identification header packet is too small: {} < {}", packet.len(), Self::SIZE)));
+		}
+		if packet[0..7] != *b"\x80theora" {
+			return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing Theora identification header: expected `\\x80theora`, got `{}`", String::from_utf8_lossy(&packet[0..7]))));
+		}
+		let read_u16 = |offset: usize|u16::from_be_bytes([packet[offset], packet[offset + 1]]) as u32;
+		let read_u24 = |offset: usize|u32::from_be_bytes([0, packet[offset], packet[offset + 1], packet[offset + 2]]);
+		let read_u32 = |offset: usize|u32::from_be_bytes(packet[offset..offset + 4].try_into().unwrap());
+		let bits = u16::from_be_bytes([packet[40], packet[41]]);
+		Ok(Self {
+			version_major: packet[7],
+			version_minor: packet[8],
+			version_revision: packet[9],
+			frame_width: read_u16(10) * 16,
+			frame_height: read_u16(12) * 16,
+			picture_width: read_u24(14),
+			picture_height: read_u24(17),
+			picture_x: packet[20],
+			picture_y: packet[21],
+			frame_rate_numerator: read_u32(22),
+			frame_rate_denominator: read_u32(26),
+			aspect_numerator: read_u24(30),
+			aspect_denominator: read_u24(33),
+			color_space: packet[36],
+			nominal_bitrate: read_u24(37),
+			quality: (bits >> 10) as u8,
+			granule_shift: ((bits >> 5) & 0x1F) as u8,
+			pixel_format: ((bits >> 3) & 0x03) as u8,
+		})
+	}
+}
+
+/// * Theora encodes the granule position as `(keyframe << shift) | frames_since_keyframe`, convert it to the frame number.
+pub fn theora_granule_to_frame(granule: u64, granule_shift: u8) -> u64 {
+	let shift = granule_shift as u32;
+	if shift >= 64 {
+		return granule;
+	}
+	(granule >> shift) + (granule & ((1u64 << shift) - 1))
+}
+
+/// * Compare two Theora granule positions by their frame numbers, the raw `u64` comparison doesn't reflect the frame order near the keyframes.
+/// * E.g. with the shift 6, `(10 << 6) | 9` is frame 19, which is after `12 << 6`, frame 12. The granules of the same frame are ordered by their raw values.
+/// * The granule position -1 of the packets without a finished frame should be filtered out before comparing.
+pub fn theora_granule_compare(a: u64, b: u64, granule_shift: u8) -> Ordering {
+	theora_granule_to_frame(a, granule_shift).cmp(&theora_granule_to_frame(b, granule_shift)).then(a.cmp(&b))
+}
+
+#[test]
+fn test_theora_ident_header() {
+	let mut packet = b"\x80theora\x03\x02\x01".to_vec();
+	packet.extend([0, 40, 0, 30]); // 640x480
+	packet.extend([0, 2, 128, 0, 1, 224, 0, 0]);
+	packet.extend(30000u32.to_be_bytes());
+	packet.extend(1001u32.to_be_bytes());
+	packet.extend([0, 0, 1, 0, 0, 1, 0]);
+	packet.extend([0, 0, 0]);
+	packet.extend(((48u16 << 10) | (6 << 5)).to_be_bytes());
+	let header = TheoraIdentHeader::parse(&packet).unwrap();
+	assert_eq!(header.version_major, 3);
+	assert_eq!(header.frame_width, 640);
+	assert_eq!(header.frame_height, 480);
+	assert_eq!(header.picture_width, 640);
+	assert_eq!(header.picture_height, 480);
+	assert_eq!(header.frame_rate_numerator, 30000);
+	assert_eq!(header.frame_rate_denominator, 1001);
+	assert_eq!(header.quality, 48);
+	assert_eq!(header.granule_shift, 6);
+	assert_eq!(header.pixel_format, 0);
+
+	assert_eq!(theora_granule_to_frame((100 << 6) | 5, 6), 105);
+	assert_eq!(theora_granule_to_frame(100 << 6, 6), 100);
+
+	let (a, b) = ((10 << 6) | 9, 12 << 6);
+	assert!(a < b);
+	assert_eq!(theora_granule_compare(a, b, 6), Ordering::Greater);
+	assert_eq!(theora_granule_compare(b, a, 6), Ordering::Less);
+	assert_eq!(theora_granule_compare(a, a, 6), Ordering::Equal);
+	// Frame 15 as a keyframe, or 5 frames after the keyframe 10
+	assert_eq!(theora_granule_compare(15 << 6, (10 << 6) | 5, 6), Ordering::Greater);
+	let mut granules = [12 << 6, (10 << 6) | 9, 10 << 6, (12 << 6) | 1];
+	granules.sort_by(|&a, &b|theora_granule_compare(a, b, 6));
+	assert_eq!(granules, [10 << 6, 12 << 6, (12 << 6) | 1, (10 << 6) | 9]);
+}
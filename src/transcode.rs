@@ -0,0 +1,105 @@
+use std::{
+	collections::HashMap,
+	fmt::Debug,
+	io::{self, Read, Write},
+	mem,
+};
+
+use crate::{OggStreamReader, OggStreamWriter};
+
+/// * Read the logical packets of every stream, apply `f` to each of them, and write them to `writer` paged by `OggStreamWriter`, e.g. for rewriting the tags or re-encoding the packets.
+/// * The lacing is recalculated for the new sizes. Each packet which had a granule position in the input ends the packet with the same granule position in the output,
+///   so the granule positions are preserved. A packet grown beyond the max packet size is split, the split packets finish no logical packets or have the last known granule position.
+/// * The packets of the multiplexed streams are written in the order of the input, and the chained streams are transcoded one after another.
+/// * A stream without its EOS packet at the end of the input doesn't get one, the unfinished logical packet of it is discarded.
+pub fn transcode<R, W>(reader: R, mut writer: W, mut f: impl FnMut(Vec<u8>) -> Vec<u8>) -> io::Result<()>
+where
+	R: Read + Debug,
+	W: Write {
+	let mut reader = OggStreamReader::new(reader);
+	reader.set_continue_after_eos(true);
+	// Each stream is paged into its own buffer, which is moved to the writer after each input packet to keep the order of the input.
+	let mut streams = HashMap::<u32, OggStreamWriter<Vec<u8>>>::new();
+	while let Some(page) = reader.get_packet()? {
+		reader.reassemble(&page);
+		let stream = streams.entry(page.stream_id).or_insert_with(||OggStreamWriter::new(Vec::new(), page.stream_id));
+		while let Some(packet) = reader.finished_packets.pop_front() {
+			stream.write_packet(&f(packet.data))?;
+			if packet.granule_position != u64::MAX {
+				stream.set_granule_position(packet.granule_position);
+				stream.seal_packet(packet.granule_position, packet.is_last_packet)?;
+			}
+		}
+		if page.is_last_page() && !stream.is_ended() {
+			// The EOS packet didn't finish any logical packet, e.g. an empty EOS packet.
+			stream.seal_packet(page.granule_position, true)?;
+		}
+		writer.write_all(&mem::take(&mut stream.writer))?;
+		if page.is_last_page() {
+			// A chained stream may reuse the stream ID.
+			streams.remove(&page.stream_id);
+		}
+	}
+	for (_, stream) in streams.drain() {
+		stream.discard();
+	}
+	writer.flush()
+}
+
+#[test]
+fn test_transcode() {
+	use std::io::Cursor;
+	use crate::{Codec, OggLogicalPacket, OggMuxer, OggPacket, validate_stream};
+	let logical_packets = |bytes: &[u8]| -> Vec<OggLogicalPacket> {
+		let mut reader = OggStreamReader::new(Cursor::new(bytes));
+		reader.set_continue_after_eos(true);
+		let mut packets = Vec::<OggLogicalPacket>::new();
+		while let Some(packet) = reader.get_logical_packet().unwrap() {
+			packets.push(packet);
+		}
+		packets
+	};
+	let granules = |bytes: &[u8]| -> Vec<(u32, u64)> {
+		OggPacket::parse_all(bytes).0.iter().filter(|p|p.granule_position != u64::MAX).map(|p|(p.stream_id, p.granule_position)).collect()
+	};
+
+	// Without changing the packets, the logical packets and the granule positions are the same
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let mut sink = Vec::<u8>::new();
+	transcode(Cursor::new(&test_ogg), &mut sink, |data|data).unwrap();
+	let expected = logical_packets(&test_ogg);
+	let transcoded = logical_packets(&sink);
+	assert_eq!(transcoded.iter().map(|p|&p.data).collect::<Vec<_>>(), expected.iter().map(|p|&p.data).collect::<Vec<_>>());
+	assert_eq!(transcoded.iter().map(|p|p.granule_position).collect::<Vec<u64>>(), expected.iter().map(|p|p.granule_position).collect::<Vec<u64>>());
+	assert_eq!(granules(&sink), granules(&test_ogg));
+	assert!(validate_stream(Cursor::new(&sink)).is_valid());
+
+	// Multiplexed streams with the packets changing their sizes
+	let mut muxer = OggMuxer::new(Vec::<u8>::new());
+	muxer.add_stream(1, Codec::Opus{pre_skip: 0}).unwrap();
+	muxer.add_stream(2, Codec::Unknown).unwrap();
+	muxer.push_packet(1, b"OpusHead", 0).unwrap();
+	muxer.push_packet(2, b"metadata", 0).unwrap();
+	for i in 1..=20u64 {
+		muxer.push_packet(1, &vec![i as u8; 3000], i * 960).unwrap();
+	}
+	let input = muxer.finish().unwrap();
+	let mut sink = Vec::<u8>::new();
+	transcode(Cursor::new(&input), &mut sink, |data|data[..data.len() / 2].repeat(3)).unwrap();
+	let transcoded = logical_packets(&sink);
+	let expected = logical_packets(&input);
+	assert_eq!(transcoded.len(), expected.len());
+	for (transcoded, expected) in transcoded.iter().zip(expected.iter()) {
+		assert_eq!(transcoded.stream_id, expected.stream_id);
+		assert_eq!(transcoded.data, expected.data[..expected.data.len() / 2].repeat(3));
+		assert_eq!(transcoded.granule_position, expected.granule_position);
+	}
+	assert_eq!(granules(&sink).iter().filter(|(id, _)|*id == 2).count(), granules(&input).iter().filter(|(id, _)|*id == 2).count());
+	assert!(validate_stream(Cursor::new(&sink)).is_valid());
+
+	// A truncated stream doesn't get an EOS packet
+	let last = OggPacket::parse_all(&test_ogg).0.last().unwrap().total_page_size();
+	let mut sink = Vec::<u8>::new();
+	transcode(Cursor::new(&test_ogg[..test_ogg.len() - last]), &mut sink, |data|data).unwrap();
+	assert!(OggPacket::parse_all(&sink).0.iter().all(|p|!p.is_last_page()));
+}
@@ -0,0 +1,226 @@
+use std::{
+	fmt::Debug,
+	io::{self, Read},
+};
+
+use crate::{Codec, OggError, OggPacket, OggStreamReader, detect_codec};
+
+/// * The statistics of a stream found by `validate_stream()` or `stream_stats()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamStats {
+	pub stream_id: u32,
+
+	/// * How many packets of the stream were read, the packets with a wrong checksum aren't counted
+	pub pages: usize,
+
+	/// * How many logical packets finished in the packets, counted by the lacing values shorter than 255
+	pub logical_packets: usize,
+
+	/// * The first granule position which isn't -1
+	pub first_granule: Option<u64>,
+
+	/// * The last granule position which isn't -1
+	pub last_granule: Option<u64>,
+
+	/// * The total data size of the packets, without the headers
+	pub payload_bytes: u64,
+
+	/// * The codec detected by the BOS packet, `Codec::Unknown` if the BOS packet wasn't seen
+	pub codec: Codec,
+
+	/// * If the BOS packet of the stream was seen
+	pub bos_seen: bool,
+
+	/// * If the EOS packet of the stream was seen
+	pub eos_seen: bool,
+}
+
+impl StreamStats {
+	fn new(stream_id: u32) -> Self {
+		Self {
+			stream_id,
+			pages: 0,
+			logical_packets: 0,
+			first_granule: None,
+			last_granule: None,
+			payload_bytes: 0,
+			codec: Codec::Unknown,
+			bos_seen: false,
+			eos_seen: false,
+		}
+	}
+
+	/// * Find the statistics of the stream of the packet, or add it in the order of the first packets
+	fn of_stream(streams: &mut Vec<Self>, stream_id: u32) -> &mut Self {
+		match streams.iter().position(|s|s.stream_id == stream_id) {
+			Some(i) => &mut streams[i],
+			None => {
+				streams.push(Self::new(stream_id));
+				streams.last_mut().unwrap()
+			}
+		}
+	}
+
+	/// * Count the packet in, only its header and segment table are looked at except for the codec detection of the BOS packet.
+	fn add_page(&mut self, packet: &OggPacket) {
+		self.pages += 1;
+		self.logical_packets += packet.segment_table.iter().filter(|&&s|s < 255).count();
+		if packet.granule_position != u64::MAX {
+			self.first_granule.get_or_insert(packet.granule_position);
+			self.last_granule = Some(packet.granule_position);
+		}
+		self.payload_bytes += packet.data.len() as u64;
+		if packet.is_first_page() && !self.bos_seen {
+			self.codec = detect_codec(&packet.data);
+		}
+		self.bos_seen |= packet.is_first_page();
+		self.eos_seen |= packet.is_last_page();
+	}
+}
+
+/// * The report of `validate_stream()`: the statistics of each stream in the order of their first packets, and all of the errors found
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StreamReport {
+	pub streams: Vec<StreamStats>,
+	pub errors: Vec<OggError>,
+}
+
+impl StreamReport {
+	/// * How many packets were read from all of the streams
+	pub fn num_pages(&self) -> usize {
+		self.streams.iter().map(|s|s.pages).sum()
+	}
+
+	/// * How many packets have a wrong checksum
+	pub fn checksum_failures(&self) -> usize {
+		self.errors.iter().filter(|e|matches!(e, OggError::ChecksumMismatch{..})).count()
+	}
+
+	/// * How many packet sequence gaps were found
+	pub fn sequence_gaps(&self) -> usize {
+		self.errors.iter().filter(|e|matches!(e, OggError::SequenceGap{..})).count()
+	}
+
+	/// * How many granule position regressions were found
+	pub fn granule_regressions(&self) -> usize {
+		self.errors.iter().filter(|e|matches!(e, OggError::GranuleRegression{..})).count()
+	}
+
+	/// * If no errors were found and every stream reached its EOS
+	pub fn is_valid(&self) -> bool {
+		self.errors.is_empty() && self.streams.iter().all(|s|s.eos_seen)
+	}
+}
+
+/// * Walk through all of the packets and collect the errors instead of stopping at the first one, for QA of the files.
+/// * The sequence gap detection and the granule position regression detection are enabled.
+/// * The packets with a wrong checksum are skipped. The walk stops at an error which it can't recover from, e.g. a truncated packet or a bad capture pattern.
+pub fn validate_stream<R>(reader: R) -> StreamReport
+where
+	R: Read + Debug {
+	let mut reader = OggStreamReader::new(reader);
+	reader.set_detect_gaps(true);
+	reader.set_detect_granule_regression(true);
+	let mut report = StreamReport::default();
+	loop {
+		match reader.get_packet() {
+			Ok(Some(packet)) => {
+				StreamStats::of_stream(&mut report.streams, packet.stream_id).add_page(&packet);
+				// Keep reading after the EOS for the other streams
+				reader.reset_stream_state();
+			}
+			Ok(None) => break,
+			Err(e) => {
+				let error = e.get_ref().and_then(|e|e.downcast_ref::<OggError>()).cloned();
+				match error {
+					Some(error @ (OggError::ChecksumMismatch{..} | OggError::SequenceGap{..} | OggError::GranuleRegression{..})) => report.errors.push(error),
+					Some(error) => {
+						report.errors.push(error);
+						break;
+					}
+					None => break,
+				}
+			}
+		}
+	}
+	report
+}
+
+/// * Collect the statistics of each stream in a single pass, in the order of their first packets, for a media info tool.
+/// * The payloads aren't decoded, only the codec is detected by the BOS packet of each stream.
+/// * Unlike `validate_stream()`, it stops at the first error and returns it.
+pub fn stream_stats<R>(reader: R) -> io::Result<Vec<StreamStats>>
+where
+	R: Read + Debug {
+	let mut reader = OggStreamReader::new(reader);
+	let mut streams = Vec::<StreamStats>::new();
+	while let Some(packet) = reader.get_packet()? {
+		StreamStats::of_stream(&mut streams, packet.stream_id).add_page(&packet);
+		// Keep reading after the EOS for the other streams
+		reader.reset_stream_state();
+	}
+	Ok(streams)
+}
+
+#[test]
+fn test_validate_stream() {
+	use std::io::Cursor;
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let report = validate_stream(Cursor::new(test_ogg.clone()));
+	assert!(report.is_valid());
+	assert_eq!(report.num_pages(), 5);
+
+	let mut corrupt = test_ogg.clone();
+	let second = crate::OggPacket::get_length(&corrupt).unwrap();
+	let third = second + crate::OggPacket::get_length(&corrupt[second..]).unwrap();
+	corrupt[third - 1] ^= 0xFF;
+	corrupt.truncate(corrupt.len() - 1);
+	let report = validate_stream(Cursor::new(corrupt));
+	assert!(!report.is_valid());
+	assert_eq!(report.checksum_failures(), 1);
+	assert_eq!(report.sequence_gaps(), 1);
+	assert_eq!(report.num_pages(), 3);
+	assert!(matches!(report.errors.last(), Some(OggError::Truncated{..})));
+	assert!(report.streams[0].bos_seen && !report.streams[0].eos_seen);
+}
+
+#[test]
+fn test_stream_stats() {
+	use std::io::Cursor;
+	use crate::{OggMuxer, OggPacket};
+	let test_ogg = std::fs::read("test.ogg").unwrap();
+	let streams = stream_stats(Cursor::new(test_ogg.clone())).unwrap();
+	let (pages, _) = OggPacket::parse_all(&test_ogg);
+	assert_eq!(streams.len(), 1);
+	let stats = &streams[0];
+	assert_eq!(stats.stream_id, 1201010866);
+	assert_eq!(stats.pages, 5);
+	assert!(matches!(stats.codec, Codec::Vorbis{..}));
+	assert_eq!(stats.first_granule, Some(0));
+	assert_eq!(stats.last_granule, Some(pages.last().unwrap().granule_position));
+	assert_eq!(stats.payload_bytes, pages.iter().map(|p|p.data.len() as u64).sum::<u64>());
+	assert!(stats.bos_seen && stats.eos_seen);
+	assert_eq!(validate_stream(Cursor::new(test_ogg.clone())).streams, streams);
+
+	// Interleaved streams, a logical packet spanning two packets is counted once
+	let mut muxer = OggMuxer::new(Vec::<u8>::new());
+	muxer.add_stream(1, Codec::Opus{pre_skip: 0}).unwrap();
+	muxer.add_stream(2, Codec::Unknown).unwrap();
+	muxer.push_packet(1, b"OpusHead\x01\x02\x38\x01\x80\xbb\0\0\0\0\0", 0).unwrap();
+	muxer.push_packet(2, b"metadata", 0).unwrap();
+	muxer.push_packet(1, &vec![0u8; 70000], 960).unwrap();
+	muxer.push_packet(1, &[0u8; 100], 1920).unwrap();
+	let streams = stream_stats(Cursor::new(muxer.finish().unwrap())).unwrap();
+	assert_eq!(streams.iter().map(|s|s.stream_id).collect::<Vec<u32>>(), vec![1, 2]);
+	assert_eq!(streams[0].codec, Codec::Opus{pre_skip: 312});
+	assert_eq!(streams[0].logical_packets, 3);
+	assert_eq!(streams[0].pages, 4);
+	assert_eq!(streams[0].last_granule, Some(1920));
+	assert_eq!(streams[0].payload_bytes, 19 + 70000 + 100);
+	assert_eq!(streams[1].codec, Codec::Unknown);
+	assert_eq!(streams[1].logical_packets, 1);
+
+	let mut truncated = test_ogg;
+	truncated.truncate(truncated.len() - 1);
+	assert_eq!(stream_stats(Cursor::new(truncated)).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,234 @@
+use std::{
+	fmt::Debug,
+	io::{self, ErrorKind, Read},
+};
+
+use crate::OggStreamReader;
+
+/// * The identification header of a Vorbis stream, which is its first header packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VorbisIdentHeader {
+	/// * The version of the Vorbis I specification, must be 0
+	pub version: u32,
+
+	/// * The number of channels
+	pub channels: u8,
+
+	/// * The sample rate in Hz
+	pub sample_rate: u32,
+
+	/// * The max bitrate, 0 means unset
+	pub bitrate_maximum: i32,
+
+	/// * The nominal bitrate, 0 means unset
+	pub bitrate_nominal: i32,
+
+	/// * The min bitrate, 0 means unset
+	pub bitrate_minimum: i32,
+
+	/// * The short block size in samples
+	pub blocksize_0: u16,
+
+	/// * The long block size in samples
+	pub blocksize_1: u16,
+}
+
+/// * Check the packet type and the `vorbis` signature of a Vorbis header packet
+fn check_header(packet: &[u8], packet_type: u8, min_size: usize, name: &str) -> io::Result<()> {
+	if packet.len() < min_size {
+		return Err(io::Error::new(ErrorKind::UnexpectedEof, format!("The Vorbis {name} header is too small: {} < {min_size}", packet.len())));
+	}
+	if packet[0] != packet_type || packet[1..7] != *b"vorbis" {
+		return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing Vorbis {name} header: expected `\\x{packet_type:02x}vorbis`, got `{}`", String::from_utf8_lossy(&packet[0..7]))));
+	}
+	Ok(())
+}
+
+impl VorbisIdentHeader {
+	/// * The size of the identification header packet
+	pub const SIZE: usize = 30;
+
+	pub fn parse(packet: &[u8]) -> io::Result<Self> {
+		check_header(packet, 1, Self::SIZE, "identification")?;
+		let read_u32 = |offset: usize|u32::from_le_bytes(packet[offset..offset + 4].try_into().unwrap());
+		let version = read_u32(7);
+		if version != 0 {
+			return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing Vorbis identification header: unsupported `version` = {version}")));
+		}
+		if packet[29] & 1 == 0 {
+			return Err(io::Error::new(ErrorKind::InvalidData, "While parsing Vorbis identification header: the framing bit is not set"));
+		}
+		Ok(Self {
+			version,
+			channels: packet[11],
+			sample_rate: read_u32(12),
+			bitrate_maximum: read_u32(16) as i32,
+			bitrate_nominal: read_u32(20) as i32,
+			bitrate_minimum: read_u32(24) as i32,
+			blocksize_0: 1 << (packet[28] & 0x0F),
+			blocksize_1: 1 << (packet[28] >> 4),
+		})
+	}
+}
+
+/// * The comment header of a Vorbis stream, which is its second header packet
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VorbisComments {
+	/// * The vendor string of the encoder
+	pub vendor: String,
+
+	/// * The comments as name-value pairs, e.g. `("TITLE", "Song")`. A comment without `=` has an empty value.
+	pub comments: Vec<(String, String)>,
+}
+
+fn comments_eof(name: &str) -> io::Error {
+	io::Error::new(ErrorKind::UnexpectedEof, format!("While parsing {name}: the packet ended unexpectedly"))
+}
+
+impl VorbisComments {
+	/// * Parse the comment header leniently: some malformed files omit the framing bit after the comments, it's tolerated.
+	/// * Use `parse_with_framing_bit()` to know if the framing bit was missing.
+	pub fn parse(packet: &[u8]) -> io::Result<Self> {
+		Self::parse_with_mode(packet, false)
+	}
+
+	/// * Parse the comment header, the framing bit after the comments must be set.
+	pub fn parse_strict(packet: &[u8]) -> io::Result<Self> {
+		Self::parse_with_mode(packet, true)
+	}
+
+	/// * Parse the comment header leniently like `parse()`, returns it along with if the framing bit is set, so a malformed file can be reported.
+	pub fn parse_with_framing_bit(packet: &[u8]) -> io::Result<(Self, bool)> {
+		check_header(packet, 3, 7, "comment")?;
+		let (ret, pos) = Self::parse_comment_list(packet, 7, "Vorbis comment header")?;
+		Ok((ret, packet.get(pos).is_some_and(|&b|b & 1 != 0)))
+	}
+
+	/// * Parse the comment header, `strict` tells if a missing framing bit is an error.
+	fn parse_with_mode(packet: &[u8], strict: bool) -> io::Result<Self> {
+		let (ret, framing_bit) = Self::parse_with_framing_bit(packet)?;
+		if strict && !framing_bit {
+			return Err(io::Error::new(ErrorKind::InvalidData, "While parsing Vorbis comment header: the framing bit is not set"));
+		}
+		Ok(ret)
+	}
+
+	/// * Parse the `OpusTags` packet, which is the second header packet of an Opus stream. It has the same comment list as the Vorbis comment header, without the framing bit.
+	pub fn parse_opus_tags(packet: &[u8]) -> io::Result<Self> {
+		if !packet.starts_with(b"OpusTags") {
+			return Err(io::Error::new(ErrorKind::InvalidData, format!("While parsing `OpusTags`: expected `OpusTags`, got `{}`", String::from_utf8_lossy(&packet[..packet.len().min(8)]))));
+		}
+		Ok(Self::parse_comment_list(packet, 8, "`OpusTags`")?.0)
+	}
+
+	/// * Parse the vendor string and the comments from `pos`, returns them along with the position after them.
+	fn parse_comment_list(packet: &[u8], mut pos: usize, name: &str) -> io::Result<(Self, usize)> {
+		let eof = ||comments_eof(name);
+		let read_string = |pos: &mut usize| -> io::Result<String> {
+			let length = u32::from_le_bytes(packet.get(*pos..*pos + 4).ok_or_else(eof)?.try_into().unwrap()) as usize;
+			let string = packet.get(*pos + 4..(*pos + 4).saturating_add(length)).ok_or_else(eof)?;
+			*pos += 4 + length;
+			Ok(String::from_utf8_lossy(string).to_string())
+		};
+		let vendor = read_string(&mut pos)?;
+		let num_comments = u32::from_le_bytes(packet.get(pos..pos + 4).ok_or_else(eof)?.try_into().unwrap());
+		pos += 4;
+		let mut comments = Vec::<(String, String)>::new();
+		for _ in 0..num_comments {
+			let comment = read_string(&mut pos)?;
+			comments.push(match comment.split_once('=') {
+				Some((name, value)) => (name.to_string(), value.to_string()),
+				None => (comment, String::new()),
+			});
+		}
+		Ok((Self {
+			vendor,
+			comments,
+		}, pos))
+	}
+
+	/// * Serialize to a comment header packet, with the framing bit
+	pub fn to_packet(&self) -> Vec<u8> {
+		fn write_string(packet: &mut Vec<u8>, string: &str) {
+			packet.extend((string.len() as u32).to_le_bytes());
+			packet.extend(string.as_bytes());
+		}
+		let mut ret = b"\x03vorbis".to_vec();
+		write_string(&mut ret, &self.vendor);
+		ret.extend((self.comments.len() as u32).to_le_bytes());
+		for (name, value) in self.comments.iter() {
+			write_string(&mut ret, &format!("{name}={value}"));
+		}
+		ret.push(1);
+		ret
+	}
+}
+
+/// * Read the three header packets of the Vorbis stream: the identification header, the comment header, and the setup header.
+/// * The header packets are reassembled from the packets, since the setup header is usually large enough to span multiple packets.
+/// * The setup header is returned as the raw packet. A missing framing bit of the comment header is only an error in the strict mode of the reader.
+/// * It's an error if the audio packets or the end of the stream come before all of the three header packets.
+pub fn read_vorbis_headers<R>(reader: &mut OggStreamReader<R>) -> io::Result<(VorbisIdentHeader, VorbisComments, Vec<u8>)>
+where
+	R: Read + Debug {
+	let mut headers = Vec::<Vec<u8>>::with_capacity(3);
+	let mut stream_id = None;
+	while headers.len() < 3 {
+		let packet = match reader.get_logical_packet()? {
+			Some(packet) => packet,
+			None => return Err(io::Error::new(ErrorKind::UnexpectedEof, format!("Only {} of the 3 Vorbis header packets were found before the end of the stream", headers.len()))),
+		};
+		if *stream_id.get_or_insert(packet.stream_id) != packet.stream_id {
+			continue;
+		}
+		if packet.data.first().is_none_or(|&t|t & 1 == 0) {
+			return Err(io::Error::new(ErrorKind::InvalidData, format!("Only {} of the 3 Vorbis header packets were found before the audio packets", headers.len())));
+		}
+		headers.push(packet.data);
+	}
+	let setup = headers.pop().unwrap();
+	check_header(&setup, 5, 7, "setup")?;
+	let comments = VorbisComments::parse_with_mode(&headers[1], reader.strict)?;
+	let ident = VorbisIdentHeader::parse(&headers[0])?;
+	Ok((ident, comments, setup))
+}
+
+#[test]
+fn test_read_vorbis_headers() {
+	use std::{fs::File, io::BufReader};
+	let mut oggreader = OggStreamReader::new(BufReader::new(File::open("test.ogg").unwrap()));
+	let (ident, comments, setup) = read_vorbis_headers(&mut oggreader).unwrap();
+	assert_eq!(ident.sample_rate, 44100);
+	assert_eq!(ident.channels, 2);
+	assert!(ident.blocksize_0 <= ident.blocksize_1);
+	assert_eq!(VorbisComments::parse(&comments.to_packet()).unwrap(), comments);
+	assert_eq!(&setup[0..7], b"\x05vorbis");
+	assert!(oggreader.get_logical_packet().unwrap().is_some_and(|p|p.data[0] & 1 == 0));
+
+	let comments = VorbisComments {
+		vendor: "tinyogg".to_string(),
+		comments: vec![("TITLE".to_string(), "Song".to_string())],
+	};
+	assert_eq!(VorbisComments::parse(&comments.to_packet()).unwrap(), comments);
+	let packet = comments.to_packet();
+	assert_eq!(packet.last(), Some(&1));
+	assert_eq!(VorbisComments::parse_strict(&packet).unwrap(), comments);
+	assert_eq!(VorbisComments::parse_with_framing_bit(&packet).unwrap(), (comments.clone(), true));
+
+	// Without the framing bit, or with the framing bit cleared
+	let mut no_framing = packet.clone();
+	no_framing.pop();
+	assert_eq!(VorbisComments::parse(&no_framing).unwrap(), comments);
+	assert_eq!(VorbisComments::parse_strict(&no_framing).unwrap_err().kind(), ErrorKind::InvalidData);
+	assert_eq!(VorbisComments::parse_with_framing_bit(&no_framing).unwrap(), (comments.clone(), false));
+	let mut cleared = packet.clone();
+	*cleared.last_mut().unwrap() = 0;
+	assert_eq!(VorbisComments::parse(&cleared).unwrap(), comments);
+	assert!(VorbisComments::parse_strict(&cleared).is_err());
+	assert_eq!(VorbisComments::parse_with_framing_bit(&cleared).unwrap(), (comments.clone(), false));
+
+	// A truncated comment is still an error
+	let mut truncated = no_framing;
+	truncated.pop();
+	assert_eq!(VorbisComments::parse(&truncated).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+}